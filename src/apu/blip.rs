@@ -0,0 +1,136 @@
+use std::{collections::VecDeque, f64::consts::PI};
+
+// band-limited step synthesis: every change of the input adds a windowed sinc step at its exact
+// sub-sample position, so content above the output Nyquist frequency is filtered instead of aliased
+pub struct BlipBuffer {
+    // output samples per dot in 32.32 fixed point
+    factor: u64,
+    // current position in 32.32 fixed point samples, relative to the first unread sample
+    time: u64,
+    kernel: Vec<[f32; BlipBuffer::TAPS]>,
+    deltas: VecDeque<f32>,
+    integrator: f32,
+}
+
+impl BlipBuffer {
+    const TAPS: usize = 16;
+    const PHASE_BITS: u32 = 6;
+    const PHASES: usize = 1 << BlipBuffer::PHASE_BITS;
+    const FRACTION_BITS: u32 = 32;
+    // the cutoff sits a bit under the output Nyquist frequency to leave room for the window
+    const CUTOFF: f64 = 0.45;
+
+    pub fn new(clock_rate: u32, sample_rate: u32) -> BlipBuffer {
+        BlipBuffer {
+            factor: (u64::from(sample_rate) << BlipBuffer::FRACTION_BITS) / u64::from(clock_rate),
+            time: 0,
+            kernel: BlipBuffer::kernel(),
+            deltas: VecDeque::new(),
+            integrator: 0.0,
+        }
+    }
+
+    // one impulse per phase, Blackman windowed and normalized so that every step has the same height
+    fn kernel() -> Vec<[f32; BlipBuffer::TAPS]> {
+        let half = BlipBuffer::TAPS as f64 / 2.0;
+        (0..BlipBuffer::PHASES)
+            .map(|phase| {
+                let center = half - 1.0 + phase as f64 / BlipBuffer::PHASES as f64;
+                let mut taps = [0.0; BlipBuffer::TAPS];
+                for (tap, value) in taps.iter_mut().enumerate() {
+                    let x = tap as f64 - center;
+                    let sinc = if x == 0.0 {
+                        1.0
+                    } else {
+                        (2.0 * PI * BlipBuffer::CUTOFF * x).sin() / (2.0 * PI * BlipBuffer::CUTOFF * x)
+                    };
+                    let window = 0.42 + 0.5 * (PI * x / half).cos() + 0.08 * (2.0 * PI * x / half).cos();
+                    *value = sinc * window;
+                }
+                let sum: f64 = taps.iter().sum();
+                let mut normalized = [0.0; BlipBuffer::TAPS];
+                for (normalized, value) in normalized.iter_mut().zip(taps.iter()) {
+                    *normalized = (value / sum) as f32;
+                }
+                normalized
+            })
+            .collect()
+    }
+
+    // adds a change of the input at the current time, it shows up in the output TAPS / 2 samples later
+    pub fn add_delta(&mut self, delta: f32) {
+        if delta == 0.0 {
+            return;
+        }
+        let index = (self.time >> BlipBuffer::FRACTION_BITS) as usize;
+        let phase = (self.time >> (BlipBuffer::FRACTION_BITS - BlipBuffer::PHASE_BITS)) as usize & (BlipBuffer::PHASES - 1);
+        if self.deltas.len() < index + BlipBuffer::TAPS {
+            self.deltas.resize(index + BlipBuffer::TAPS, 0.0);
+        }
+        for (tap, value) in self.kernel[phase].iter().enumerate() {
+            self.deltas[index + tap] += delta * value;
+        }
+    }
+
+    pub fn advance(&mut self, dots: u32) {
+        self.time += u64::from(dots) * self.factor;
+    }
+
+    // samples that no future delta can change anymore
+    pub fn available(&self) -> usize {
+        (self.time >> BlipBuffer::FRACTION_BITS) as usize
+    }
+
+    pub fn read_sample(&mut self) -> Option<f32> {
+        if self.available() == 0 {
+            return None;
+        }
+        self.time -= 1 << BlipBuffer::FRACTION_BITS;
+        self.integrator += self.deltas.pop_front().unwrap_or(0.0);
+        Some(self.integrator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blip_step_test() {
+        // 4 dots per sample, a step in the middle of sample 2 settles after the kernel delay
+        let mut blip = BlipBuffer::new(16, 4);
+        blip.advance(10);
+        blip.add_delta(1.0);
+        blip.advance(30 * 4 - 10);
+        assert_eq!(blip.available(), 30);
+        let samples: Vec<f32> = (0..30).map(|_| blip.read_sample().unwrap()).collect();
+        assert_eq!(blip.read_sample(), None);
+        assert_eq!(samples[0], 0.0);
+        assert_eq!(samples[1], 0.0);
+        assert!((samples[2 + BlipBuffer::TAPS / 2 - 1] - 0.5).abs() < 0.25);
+        for sample in samples[2 + BlipBuffer::TAPS..].iter() {
+            assert!((sample - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn blip_aliasing_test() {
+        // a 524 kHz square wave has nothing below 48 kHz but its average, point sampling it would
+        // only ever see one of its levels
+        let mut blip = BlipBuffer::new(4_194_304, 48_000);
+        let mut level = 0.0;
+        for _ in 0..100_000 {
+            let next = 1.0 - level;
+            blip.add_delta(next - level);
+            level = next;
+            blip.advance(4);
+        }
+        let mut samples = Vec::new();
+        while let Some(sample) = blip.read_sample() {
+            samples.push(sample);
+        }
+        for sample in samples[BlipBuffer::TAPS..].iter() {
+            assert!((sample - 0.5).abs() < 0.05, "{}", sample);
+        }
+    }
+}
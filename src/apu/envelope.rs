@@ -0,0 +1,65 @@
+// volume envelope driven by NRx2, shared by the pulse and noise channels
+pub struct Envelope {
+    register: u8,
+    volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    pub fn new() -> Envelope {
+        Envelope {
+            register: 0x00,
+            volume: 0,
+            timer: 8,
+        }
+    }
+
+    pub fn read_register(&self) -> u8 {
+        self.register
+    }
+
+    pub fn write_register(&mut self, value: u8) {
+        self.register = value;
+    }
+
+    // the upper 5 bits of NRx2 power the channel DAC
+    pub fn dac_enabled(&self) -> bool {
+        self.register & 0xF8 != 0
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.register >> 4;
+        self.timer = self.period();
+    }
+
+    // clocked at 64 Hz by the frame sequencer
+    pub fn clock(&mut self) {
+        if self.register & 0x07 == 0 {
+            return;
+        }
+        self.timer -= 1;
+        if self.timer > 0 {
+            return;
+        }
+        self.timer = self.period();
+        if self.register & 0x08 != 0 {
+            if self.volume < 15 {
+                self.volume += 1;
+            }
+        } else if self.volume > 0 {
+            self.volume -= 1;
+        }
+    }
+
+    // a period of 0 is treated as 8
+    fn period(&self) -> u8 {
+        match self.register & 0x07 {
+            0 => 8,
+            period => period,
+        }
+    }
+}
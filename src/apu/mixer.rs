@@ -0,0 +1,74 @@
+// the output capacitor that removes the DC offset of the DACs
+pub struct HighPassFilter {
+    capacitor: f32,
+    charge_factor: f32,
+}
+
+impl HighPassFilter {
+    // fraction of the charge the capacitor keeps per dot
+    const DMG_CHARGE_FACTOR: f64 = 0.999958;
+    const CGB_CHARGE_FACTOR: f64 = 0.998943;
+
+    pub fn new(sample_rate: u32, cgb: bool) -> HighPassFilter {
+        let dots_per_sample = f64::from(super::Apu::CLOCK_RATE) / f64::from(sample_rate);
+        let charge_factor = if cgb {
+            HighPassFilter::CGB_CHARGE_FACTOR
+        } else {
+            HighPassFilter::DMG_CHARGE_FACTOR
+        };
+        HighPassFilter {
+            capacitor: 0.0,
+            charge_factor: charge_factor.powf(dots_per_sample) as f32,
+        }
+    }
+
+    // the capacitor only charges while at least one DAC is on
+    pub fn filter(&mut self, input: f32, dacs_enabled: bool) -> f32 {
+        if !dacs_enabled {
+            return 0.0;
+        }
+        let output = input - self.capacitor;
+        self.capacitor = input - output * self.charge_factor;
+        output
+    }
+}
+
+// turns a digital amplitude from 0 to 15 into the -1.0 to 1.0 range, a disabled DAC outputs 0
+pub fn dac_output(amplitude: u8, dac_enabled: bool) -> f32 {
+    if dac_enabled {
+        f32::from(amplitude) / 7.5 - 1.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apu::Apu;
+
+    // with a constant input the second output is what the capacitor let through
+    fn second_output(mut filter: HighPassFilter) -> f32 {
+        assert_eq!(filter.filter(1.0, true), 1.0);
+        filter.filter(1.0, true)
+    }
+
+    #[test]
+    fn high_pass_filter_test() {
+        let dmg = second_output(HighPassFilter::new(Apu::CLOCK_RATE, false));
+        assert!((dmg - 0.999958).abs() < 1e-6);
+        let cgb = second_output(HighPassFilter::new(Apu::CLOCK_RATE, true));
+        assert!((cgb - 0.998943).abs() < 1e-6);
+
+        // at 4 dots per sample the capacitor charges for 4 dots in between
+        let dmg = second_output(HighPassFilter::new(Apu::CLOCK_RATE / 4, false));
+        assert!((dmg - 0.999958f32.powi(4)).abs() < 1e-6);
+        let cgb = second_output(HighPassFilter::new(Apu::CLOCK_RATE / 4, true));
+        assert!((cgb - 0.998943f32.powi(4)).abs() < 1e-6);
+
+        // nothing charges while every DAC is off
+        let mut filter = HighPassFilter::new(Apu::CLOCK_RATE, false);
+        assert_eq!(filter.filter(1.0, false), 0.0);
+        assert_eq!(filter.filter(1.0, true), 1.0);
+    }
+}
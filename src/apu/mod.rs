@@ -0,0 +1,504 @@
+mod blip;
+mod envelope;
+mod length;
+mod mixer;
+mod noise;
+mod pulse;
+mod wave;
+
+use blip::BlipBuffer;
+use mixer::HighPassFilter;
+use noise::Noise;
+use pulse::Pulse;
+use std::collections::VecDeque;
+use wave::Wave;
+
+pub struct Apu {
+    channel1: Pulse,
+    channel2: Pulse,
+    channel3: Wave,
+    channel4: Noise,
+    cgb: bool,
+    powered: bool,
+    // NR50 master volume and NR51 channel panning
+    volume: u8,
+    panning: u8,
+    frame_sequencer_step: u8,
+    sample_rate: u32,
+    // mixer output as of the last step, changes go into the buffers as deltas
+    levels: (f32, f32),
+    left_buffer: BlipBuffer,
+    right_buffer: BlipBuffer,
+    left_filter: HighPassFilter,
+    right_filter: HighPassFilter,
+    // interleaved left and right samples
+    samples: VecDeque<i16>,
+}
+
+impl Apu {
+    const NR10_ADDRESS: u16 = 0xFF10;
+    const NR20_ADDRESS: u16 = 0xFF15;
+    const NR24_ADDRESS: u16 = 0xFF19;
+    const NR30_ADDRESS: u16 = 0xFF1A;
+    const NR34_ADDRESS: u16 = 0xFF1E;
+    const NR40_ADDRESS: u16 = 0xFF1F;
+    const NR44_ADDRESS: u16 = 0xFF23;
+    const NR50_ADDRESS: u16 = 0xFF24;
+    const NR51_ADDRESS: u16 = 0xFF25;
+    const NR52_ADDRESS: u16 = 0xFF26;
+    const WAVE_RAM_START: u16 = 0xFF30;
+    const WAVE_RAM_END: u16 = 0xFF3F;
+
+    const POWER: u8 = 0x80;
+
+    pub const CLOCK_RATE: u32 = 4_194_304;
+    pub const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+
+    pub fn new() -> Apu {
+        Apu {
+            channel1: Pulse::new(true),
+            channel2: Pulse::new(false),
+            channel3: Wave::new(),
+            channel4: Noise::new(),
+            cgb: false,
+            powered: false,
+            volume: 0x00,
+            panning: 0x00,
+            frame_sequencer_step: 0,
+            sample_rate: Apu::DEFAULT_SAMPLE_RATE,
+            levels: (0.0, 0.0),
+            left_buffer: BlipBuffer::new(Apu::CLOCK_RATE, Apu::DEFAULT_SAMPLE_RATE),
+            right_buffer: BlipBuffer::new(Apu::CLOCK_RATE, Apu::DEFAULT_SAMPLE_RATE),
+            left_filter: HighPassFilter::new(Apu::DEFAULT_SAMPLE_RATE, false),
+            right_filter: HighPassFilter::new(Apu::DEFAULT_SAMPLE_RATE, false),
+            samples: VecDeque::new(),
+        }
+    }
+
+    // the CGB output capacitor discharges faster and powering off also clears the length counters
+    pub fn cgb() -> Apu {
+        Apu {
+            cgb: true,
+            left_filter: HighPassFilter::new(Apu::DEFAULT_SAMPLE_RATE, true),
+            right_filter: HighPassFilter::new(Apu::DEFAULT_SAMPLE_RATE, true),
+            ..Apu::new()
+        }
+    }
+
+    // leaves the registers as the DMG boot ROM does, without replaying its sound
+    pub fn skip_boot(&mut self) {
+        const POST_BOOT_REGISTERS: [(u16, u8); 19] = [
+            (0xFF26, 0x80),
+            (0xFF10, 0x80),
+            (0xFF11, 0xBF),
+            // the boot sound leaves channel 1 on with its envelope down to 0
+            (0xFF12, 0x08),
+            (0xFF13, 0xFF),
+            (0xFF14, 0x87),
+            (0xFF12, 0xF3),
+            (0xFF16, 0x3F),
+            (0xFF18, 0xFF),
+            (0xFF19, 0x3F),
+            (0xFF1A, 0x7F),
+            (0xFF1B, 0xFF),
+            (0xFF1C, 0x9F),
+            (0xFF1D, 0xFF),
+            (0xFF1E, 0x3F),
+            (0xFF20, 0xFF),
+            (0xFF23, 0x3F),
+            (0xFF24, 0x77),
+            (0xFF25, 0xF3),
+        ];
+
+        for (address, value) in POST_BOOT_REGISTERS.iter() {
+            self.write_byte(*address, *value);
+        }
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            Apu::NR10_ADDRESS..=0xFF14 => self.channel1.read_register(address - Apu::NR10_ADDRESS),
+            Apu::NR20_ADDRESS..=Apu::NR24_ADDRESS => self.channel2.read_register(address - Apu::NR20_ADDRESS),
+            Apu::NR30_ADDRESS..=Apu::NR34_ADDRESS => self.channel3.read_register(address - Apu::NR30_ADDRESS),
+            Apu::NR40_ADDRESS..=Apu::NR44_ADDRESS => self.channel4.read_register(address - Apu::NR40_ADDRESS),
+            Apu::NR50_ADDRESS => self.volume,
+            Apu::NR51_ADDRESS => self.panning,
+            Apu::NR52_ADDRESS => {
+                let channels = [
+                    self.channel1.is_enabled(),
+                    self.channel2.is_enabled(),
+                    self.channel3.is_enabled(),
+                    self.channel4.is_enabled(),
+                ];
+                let status = channels
+                    .iter()
+                    .enumerate()
+                    .fold(0, |status, (channel, enabled)| status | (*enabled as u8) << channel);
+                0x70 | if self.powered { Apu::POWER } else { 0 } | status
+            }
+            Apu::WAVE_RAM_START..=Apu::WAVE_RAM_END => self.channel3.read_ram(address - Apu::WAVE_RAM_START),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_byte(&mut self, address: u16, mut value: u8) {
+        // while powered off only NR52, wave RAM and, on DMG, the length counters can be written
+        if !self.powered {
+            match address {
+                Apu::NR52_ADDRESS | Apu::WAVE_RAM_START..=Apu::WAVE_RAM_END => (),
+                0xFF11 | 0xFF16 | 0xFF1B | 0xFF20 if self.cgb => return,
+                0xFF11 | 0xFF16 => value &= 0x3F,
+                0xFF1B | 0xFF20 => (),
+                _ => return,
+            }
+        }
+
+        match address {
+            Apu::NR10_ADDRESS..=0xFF14 => self.channel1.write_register(address - Apu::NR10_ADDRESS, value),
+            Apu::NR20_ADDRESS..=Apu::NR24_ADDRESS => self.channel2.write_register(address - Apu::NR20_ADDRESS, value),
+            Apu::NR30_ADDRESS..=Apu::NR34_ADDRESS => self.channel3.write_register(address - Apu::NR30_ADDRESS, value),
+            Apu::NR40_ADDRESS..=Apu::NR44_ADDRESS => self.channel4.write_register(address - Apu::NR40_ADDRESS, value),
+            Apu::NR50_ADDRESS => self.volume = value,
+            Apu::NR51_ADDRESS => self.panning = value,
+            Apu::NR52_ADDRESS => self.set_powered(value & Apu::POWER != 0),
+            Apu::WAVE_RAM_START..=Apu::WAVE_RAM_END => self.channel3.write_ram(address - Apu::WAVE_RAM_START, value),
+            _ => (),
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.levels = (0.0, 0.0);
+        self.left_buffer = BlipBuffer::new(Apu::CLOCK_RATE, sample_rate);
+        self.right_buffer = BlipBuffer::new(Apu::CLOCK_RATE, sample_rate);
+        self.left_filter = HighPassFilter::new(sample_rate, self.cgb);
+        self.right_filter = HighPassFilter::new(sample_rate, self.cgb);
+        self.samples.clear();
+    }
+
+    // interleaved stereo samples produced since the last call, at most the latest second of them is kept
+    pub fn take_samples(&mut self) -> Vec<i16> {
+        self.samples.drain(..).collect()
+    }
+
+    // meant for audio callbacks: copies the oldest samples into out and pads it with silence when
+    // the APU is behind, returns how many samples it actually had. out holds whole left and right
+    // frames, an odd trailing sample is left silent so the channels never swap
+    pub fn fill_audio(&mut self, out: &mut [i16]) -> usize {
+        debug_assert!(out.len() & 1 == 0, "fill_audio needs an even number of samples");
+        let count = out.len().min(self.samples.len()) & !1;
+        for (out, sample) in out.iter_mut().zip(self.samples.drain(..count)) {
+            *out = sample;
+        }
+        for out in out[count..].iter_mut() {
+            *out = 0;
+        }
+        count
+    }
+
+    pub fn step(&mut self, cycles: u32) {
+        if self.powered {
+            self.channel1.step(cycles);
+            self.channel2.step(cycles);
+            self.channel3.step(cycles);
+            self.channel4.step(cycles);
+        }
+
+        // the mixer is sampled once per step, the CPU steps the APU every M-cycle
+        let (left, right) = self.mix();
+        self.left_buffer.advance(cycles);
+        self.right_buffer.advance(cycles);
+        self.left_buffer.add_delta(left - self.levels.0);
+        self.right_buffer.add_delta(right - self.levels.1);
+        self.levels = (left, right);
+        self.push_samples();
+    }
+
+    // advances the 512 Hz frame sequencer: length counters run at 256 Hz, the sweep at 128 Hz and envelopes at 64 Hz
+    pub fn clock_frame_sequencer(&mut self) {
+        if !self.powered {
+            return;
+        }
+        let step = self.frame_sequencer_step;
+        self.frame_sequencer_step = (step + 1) % 8;
+        if let 0 | 2 | 4 | 6 = step {
+            self.channel1.clock_length();
+            self.channel2.clock_length();
+            self.channel3.clock_length();
+            self.channel4.clock_length();
+        }
+        if let 2 | 6 = step {
+            self.channel1.clock_sweep();
+        }
+        if step == 7 {
+            self.channel1.clock_envelope();
+            self.channel2.clock_envelope();
+            self.channel4.clock_envelope();
+        }
+    }
+
+    // digital amplitudes from 0 to 15, one per channel, before any mixing
+    pub fn channel_outputs(&self) -> [u8; 4] {
+        [
+            self.channel1.output(),
+            self.channel2.output(),
+            self.channel3.output(),
+            self.channel4.output(),
+        ]
+    }
+
+    fn set_powered(&mut self, powered: bool) {
+        if self.powered && !powered {
+            // only DMG keeps the length counters
+            let keep_length = !self.cgb;
+            self.channel1.power_off(keep_length);
+            self.channel2.power_off(keep_length);
+            self.channel3.power_off(keep_length);
+            self.channel4.power_off(keep_length);
+            self.volume = 0x00;
+            self.panning = 0x00;
+        } else if !self.powered && powered {
+            self.frame_sequencer_step = 0;
+        }
+        self.powered = powered;
+    }
+
+    // sums the channels routed to each side, each side scaled by its NR50 volume from 1/8 to 8/8
+    fn mix(&self) -> (f32, f32) {
+        let dacs = self.dacs_enabled();
+        let (mut left, mut right) = (0.0, 0.0);
+        for (channel, (output, dac_enabled)) in self.channel_outputs().iter().zip(dacs.iter()).enumerate() {
+            let output = mixer::dac_output(*output, *dac_enabled);
+            if self.panning & (0x10 << channel) != 0 {
+                left += output;
+            }
+            if self.panning & (0x01 << channel) != 0 {
+                right += output;
+            }
+        }
+        let left_volume = f32::from((self.volume >> 4) & 0x07) + 1.0;
+        let right_volume = f32::from(self.volume & 0x07) + 1.0;
+        (left / 4.0 * left_volume / 8.0, right / 4.0 * right_volume / 8.0)
+    }
+
+    fn dacs_enabled(&self) -> [bool; 4] {
+        [
+            self.channel1.dac_enabled(),
+            self.channel2.dac_enabled(),
+            self.channel3.dac_enabled(),
+            self.channel4.dac_enabled(),
+        ]
+    }
+
+    // both buffers advance by the same dots, so they always have the same number of samples ready
+    fn push_samples(&mut self) {
+        let dacs_enabled = self.dacs_enabled().contains(&true);
+        while let (Some(left), Some(right)) = (self.left_buffer.read_sample(), self.right_buffer.read_sample()) {
+            let left = self.left_filter.filter(left, dacs_enabled);
+            let right = self.right_filter.filter(right, dacs_enabled);
+            // the filter can swing up to twice the mixer range, float to int casts saturate
+            self.samples.push_back((left * 16384.0) as i16);
+            self.samples.push_back((right * 16384.0) as i16);
+            if self.samples.len() > self.sample_rate as usize * 2 {
+                self.samples.pop_front();
+                self.samples.pop_front();
+            }
+        }
+    }
+}
+
+impl Default for Apu {
+    fn default() -> Apu {
+        Apu::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn powered_apu() -> Apu {
+        let mut apu = Apu::new();
+        apu.write_byte(0xFF26, 0x80);
+        apu
+    }
+
+    #[test]
+    fn apu_registers_test() {
+        let mut apu = Apu::new();
+        apu.skip_boot();
+        assert_eq!(apu.read_byte(0xFF10), 0x80);
+        assert_eq!(apu.read_byte(0xFF11), 0xBF);
+        assert_eq!(apu.read_byte(0xFF12), 0xF3);
+        assert_eq!(apu.read_byte(0xFF14), 0xBF);
+        assert_eq!(apu.read_byte(0xFF15), 0xFF);
+        assert_eq!(apu.read_byte(0xFF16), 0x3F);
+        assert_eq!(apu.read_byte(0xFF17), 0x00);
+        assert_eq!(apu.read_byte(0xFF19), 0xBF);
+        assert_eq!(apu.read_byte(0xFF1A), 0x7F);
+        assert_eq!(apu.read_byte(0xFF1C), 0x9F);
+        assert_eq!(apu.read_byte(0xFF1E), 0xBF);
+        assert_eq!(apu.read_byte(0xFF1F), 0xFF);
+        assert_eq!(apu.read_byte(0xFF20), 0xFF);
+        assert_eq!(apu.read_byte(0xFF22), 0x00);
+        assert_eq!(apu.read_byte(0xFF23), 0xBF);
+        assert_eq!(apu.channel_outputs(), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn apu_pulse_output_test() {
+        let mut apu = powered_apu();
+        // 50% duty, full volume, period of 8 dots per duty step
+        apu.write_byte(0xFF16, 0x80);
+        apu.write_byte(0xFF17, 0xF0);
+        apu.write_byte(0xFF18, 0xFE);
+        apu.write_byte(0xFF19, 0x87);
+
+        let mut outputs = Vec::new();
+        for _ in 0..8 {
+            apu.step(8);
+            outputs.push(apu.channel_outputs()[1]);
+        }
+        assert_eq!(outputs, vec![0, 0, 0, 0, 15, 15, 15, 15]);
+        assert_eq!(apu.channel_outputs()[0], 0);
+    }
+
+    #[test]
+    fn apu_frame_sequencer_test() {
+        let mut apu = powered_apu();
+        // length 2 with a decreasing envelope of period 1
+        apu.write_byte(0xFF16, 0xBE);
+        apu.write_byte(0xFF17, 0xF1);
+        apu.write_byte(0xFF18, 0x00);
+        apu.write_byte(0xFF19, 0xC0);
+        apu.step(4096);
+        assert_eq!(apu.channel_outputs()[1], 15);
+
+        // steps 0 and 2 clock the length counter, which runs out before the envelope at step 7
+        apu.clock_frame_sequencer();
+        assert_eq!(apu.channel_outputs()[1], 15);
+        apu.clock_frame_sequencer();
+        apu.clock_frame_sequencer();
+        assert_eq!(apu.channel_outputs()[1], 0);
+
+        apu.write_byte(0xFF19, 0x80);
+        for _ in 0..5 {
+            apu.clock_frame_sequencer();
+        }
+        assert_eq!(apu.channel_outputs()[1], 14);
+    }
+
+    #[test]
+    fn apu_power_test() {
+        let mut apu = Apu::new();
+        apu.skip_boot();
+        assert_eq!(apu.read_byte(0xFF24), 0x77);
+        assert_eq!(apu.read_byte(0xFF25), 0xF3);
+        assert_eq!(apu.read_byte(0xFF26), 0xF1);
+
+        // powering off clears every register but keeps wave RAM
+        apu.write_byte(0xFF30, 0x12);
+        apu.write_byte(0xFF26, 0x00);
+        assert_eq!(apu.read_byte(0xFF26), 0x70);
+        assert_eq!(apu.read_byte(0xFF24), 0x00);
+        assert_eq!(apu.read_byte(0xFF25), 0x00);
+        assert_eq!(apu.read_byte(0xFF11), 0x3F);
+        assert_eq!(apu.read_byte(0xFF12), 0x00);
+        assert_eq!(apu.read_byte(0xFF30), 0x12);
+
+        // only wave RAM and the length counters stay writable
+        apu.write_byte(0xFF12, 0xF0);
+        apu.write_byte(0xFF24, 0x77);
+        apu.write_byte(0xFF31, 0x34);
+        apu.write_byte(0xFF16, 0xFF);
+        assert_eq!(apu.read_byte(0xFF12), 0x00);
+        assert_eq!(apu.read_byte(0xFF24), 0x00);
+        assert_eq!(apu.read_byte(0xFF31), 0x34);
+        assert_eq!(apu.read_byte(0xFF16), 0x3F);
+        apu.clock_frame_sequencer();
+
+        // the length of 1 written while off runs out on the first step after powering on
+        apu.write_byte(0xFF26, 0x80);
+        apu.write_byte(0xFF17, 0xF0);
+        apu.write_byte(0xFF19, 0xC0);
+        assert_eq!(apu.read_byte(0xFF26), 0xF2);
+        apu.clock_frame_sequencer();
+        assert_eq!(apu.read_byte(0xFF26), 0xF0);
+    }
+
+    #[test]
+    fn apu_mixer_test() {
+        let mut apu = powered_apu();
+        // channel 2 holds 15 from the second half of its 50% duty cycle
+        apu.write_byte(0xFF16, 0x80);
+        apu.write_byte(0xFF17, 0xF0);
+        apu.write_byte(0xFF18, 0xFE);
+        apu.write_byte(0xFF19, 0x87);
+        apu.step(40);
+        assert_eq!(apu.channel_outputs()[1], 15);
+
+        apu.write_byte(0xFF24, 0x70);
+        apu.write_byte(0xFF25, 0x20);
+        assert_eq!(apu.mix(), (0.25, 0.0));
+        apu.write_byte(0xFF24, 0x30);
+        apu.write_byte(0xFF25, 0x22);
+        assert_eq!(apu.mix(), (0.125, 0.03125));
+
+        // a DAC that is on but not playing sits at the bottom of its range
+        apu.write_byte(0xFF12, 0xF0);
+        apu.write_byte(0xFF25, 0x11);
+        assert_eq!(apu.mix(), (-0.125, -0.03125));
+    }
+
+    #[test]
+    fn apu_sample_stream_test() {
+        let mut apu = powered_apu();
+        // 128 dots per sample
+        apu.set_sample_rate(32_768);
+        apu.write_byte(0xFF24, 0x77);
+        apu.write_byte(0xFF25, 0x22);
+        apu.write_byte(0xFF17, 0xF0);
+        apu.step(127);
+        assert!(apu.take_samples().is_empty());
+        apu.step(1);
+        assert_eq!(apu.take_samples(), vec![0, 0]);
+
+        // the step from the DAC turning on arrives after the resampling delay
+        apu.step(128 * 16);
+        let samples = apu.take_samples();
+        assert_eq!(samples.len(), 32);
+        assert!(samples[2] > -100);
+        assert!(samples[30] < -3500);
+        assert_eq!(samples[30], samples[31]);
+
+        // the high-pass filter pulls a constant DAC output back to 0
+        apu.step(128 * 4096);
+        let samples = apu.take_samples();
+        assert!(samples[samples.len() - 1].abs() < 16);
+
+        // at most the latest second of samples is buffered
+        apu.step(Apu::CLOCK_RATE * 2);
+        assert_eq!(apu.take_samples().len(), 32_768 * 2);
+    }
+
+    #[test]
+    fn apu_fill_audio_test() {
+        let mut apu = powered_apu();
+        apu.set_sample_rate(32_768);
+        // the left side is 8 times louder, which tells the two halves of a frame apart
+        apu.write_byte(0xFF24, 0x70);
+        apu.write_byte(0xFF25, 0x22);
+        apu.write_byte(0xFF17, 0xF0);
+        for _ in 0..40 {
+            apu.step(128);
+        }
+
+        let mut out = [1; 48];
+        assert_eq!(apu.fill_audio(&mut out[..16]), 16);
+        assert_eq!(apu.fill_audio(&mut out), 48);
+        assert!(out[46] < out[47] && out[47] < 0);
+        assert_eq!(apu.fill_audio(&mut out), 16);
+        assert!(out[14] < out[15]);
+        assert_eq!(out[16..], [0; 32]);
+    }
+}
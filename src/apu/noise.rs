@@ -0,0 +1,226 @@
+use super::{envelope::Envelope, length::LengthCounter};
+use std::mem;
+
+// channel 4 outputs the low bit of a linear feedback shift register
+pub struct Noise {
+    // NR43: clock shift, LFSR width and divisor code
+    polynomial: u8,
+    lfsr: u16,
+    timer: u32,
+    length: LengthCounter,
+    envelope: Envelope,
+    enabled: bool,
+}
+
+impl Noise {
+    const TRIGGER: u8 = 0x80;
+    const LENGTH_ENABLE: u8 = 0x40;
+    const SHORT_MODE: u8 = 0x08;
+
+    pub fn new() -> Noise {
+        Noise {
+            polynomial: 0x00,
+            lfsr: 0x7FFF,
+            timer: 0,
+            length: LengthCounter::new(64),
+            envelope: Envelope::new(),
+            enabled: false,
+        }
+    }
+
+    // index 0 - 4 selects NR40 - NR44, NR40 does not exist and write-only bits read as 1
+    pub fn read_register(&self, index: u16) -> u8 {
+        match index {
+            2 => self.envelope.read_register(),
+            3 => self.polynomial,
+            4 => 0xBF | if self.length.is_enabled() { Noise::LENGTH_ENABLE } else { 0 },
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_register(&mut self, index: u16, value: u8) {
+        match index {
+            1 => self.length.load(value),
+            2 => {
+                self.envelope.write_register(value);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.polynomial = value,
+            4 => {
+                self.length.set_enabled(value & Noise::LENGTH_ENABLE != 0);
+                if value & Noise::TRIGGER != 0 {
+                    self.trigger();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    // on DMG the length counter survives the APU being powered off
+    pub fn power_off(&mut self, keep_length: bool) {
+        let length = if keep_length {
+            mem::replace(&mut self.length, LengthCounter::new(64))
+        } else {
+            LengthCounter::new(64)
+        };
+        *self = Noise { length, ..Noise::new() };
+        self.length.set_enabled(false);
+    }
+
+    // digital amplitude from 0 to 15
+    pub fn output(&self) -> u8 {
+        if self.enabled && self.lfsr & 0x01 == 0 {
+            self.envelope.volume()
+        } else {
+            0
+        }
+    }
+
+    pub fn step(&mut self, cycles: u32) {
+        // clock shifts 14 and 15 stop the LFSR
+        if self.polynomial >> 4 >= 14 {
+            return;
+        }
+        let mut cycles = cycles;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.shift_lfsr();
+        }
+        self.timer -= cycles;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    // divisor codes 0 - 7 stand for 8, 16, 32 ... 112 dots, shifted left by the clock shift
+    fn period(&self) -> u32 {
+        let divisor = match self.polynomial & 0x07 {
+            0 => 8,
+            code => u32::from(code) * 16,
+        };
+        divisor << (self.polynomial >> 4)
+    }
+
+    fn shift_lfsr(&mut self) {
+        let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 0x01;
+        self.lfsr = (self.lfsr >> 1) | feedback << 14;
+        // the 7-bit mode also feeds back into bit 6, repeating every 127 steps
+        if self.polynomial & Noise::SHORT_MODE != 0 {
+            self.lfsr = (self.lfsr & !0x40) | feedback << 6;
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.timer = self.period();
+        self.envelope.trigger();
+        self.lfsr = 0x7FFF;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triggered_noise(polynomial: u8) -> Noise {
+        let mut noise = Noise::new();
+        noise.write_register(2, 0xF0);
+        noise.write_register(3, polynomial);
+        noise.write_register(4, 0x80);
+        noise
+    }
+
+    // counts the steps until the LFSR is back to its state right after a trigger
+    fn lfsr_period(noise: &mut Noise) -> u32 {
+        let mut steps = 0;
+        loop {
+            noise.step(8);
+            steps += 1;
+            if noise.lfsr == 0x7FFF {
+                return steps;
+            }
+        }
+    }
+
+    #[test]
+    fn noise_lfsr_test() {
+        let mut noise = triggered_noise(0x00);
+        assert_eq!(noise.output(), 0);
+        // all ones shift in a zero at bit 14 first, the output follows bit 0
+        noise.step(8);
+        assert_eq!(noise.lfsr, 0x3FFF);
+        for _ in 0..13 {
+            noise.step(8);
+        }
+        assert_eq!(noise.lfsr, 0x0001);
+        assert_eq!(noise.output(), 0);
+        noise.step(8);
+        assert_eq!(noise.lfsr, 0x4000);
+        assert_eq!(noise.output(), 15);
+
+        let mut noise = triggered_noise(0x00);
+        assert_eq!(lfsr_period(&mut noise), 0x7FFF);
+        let mut noise = triggered_noise(0x08);
+        assert_eq!(noise.read_register(3), 0x08);
+        noise.step(8 * 200);
+        let state = noise.lfsr & 0x7F;
+        noise.step(8 * 127);
+        assert_eq!(noise.lfsr & 0x7F, state);
+    }
+
+    #[test]
+    fn noise_frequency_test() {
+        // divisor code 3 with clock shift 2 steps every 3 * 16 << 2 dots
+        let mut noise = triggered_noise(0x23);
+        noise.step(191);
+        assert_eq!(noise.lfsr, 0x7FFF);
+        noise.step(1);
+        assert_eq!(noise.lfsr, 0x3FFF);
+
+        // clock shifts 14 and 15 never step
+        let mut noise = triggered_noise(0xE0);
+        noise.step(1 << 20);
+        assert_eq!(noise.lfsr, 0x7FFF);
+    }
+
+    #[test]
+    fn noise_envelope_and_length_test() {
+        let mut noise = triggered_noise(0x00);
+        noise.write_register(1, 0x3F);
+        noise.write_register(2, 0x31);
+        noise.write_register(4, 0xC0);
+        noise.step(8 * 15);
+        assert_eq!(noise.output(), 3);
+        noise.clock_envelope();
+        assert_eq!(noise.output(), 2);
+        noise.clock_length();
+        assert_eq!(noise.output(), 0);
+        assert_eq!(noise.read_register(4), 0xFF);
+
+        assert_eq!(noise.read_register(0), 0xFF);
+        assert_eq!(noise.read_register(1), 0xFF);
+        assert_eq!(noise.read_register(2), 0x31);
+        noise.write_register(2, 0x00);
+        noise.write_register(4, 0x80);
+        assert!(!noise.enabled);
+    }
+}
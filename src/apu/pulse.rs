@@ -0,0 +1,381 @@
+use super::{envelope::Envelope, length::LengthCounter};
+use std::mem;
+
+// channel 1 frequency sweep driven by NR10
+struct Sweep {
+    register: u8,
+    enabled: bool,
+    shadow_frequency: u16,
+    timer: u8,
+    // leaving negate mode after a negated calculation disables the channel
+    negate_used: bool,
+}
+
+impl Sweep {
+    const NEGATE: u8 = 0x08;
+
+    fn new() -> Sweep {
+        Sweep {
+            register: 0x00,
+            enabled: false,
+            shadow_frequency: 0,
+            timer: 8,
+            negate_used: false,
+        }
+    }
+
+    fn period(&self) -> u8 {
+        (self.register >> 4) & 0x07
+    }
+
+    fn shift(&self) -> u8 {
+        self.register & 0x07
+    }
+
+    fn reload_timer(&mut self) {
+        // a period of 0 is treated as 8
+        self.timer = match self.period() {
+            0 => 8,
+            period => period,
+        };
+    }
+
+    // returns None when the new frequency overflows, which disables the channel
+    fn calculate(&mut self) -> Option<u16> {
+        let delta = self.shadow_frequency >> self.shift();
+        let frequency = if self.register & Sweep::NEGATE != 0 {
+            self.negate_used = true;
+            self.shadow_frequency - delta
+        } else {
+            self.shadow_frequency + delta
+        };
+        if frequency > Pulse::MAX_FREQUENCY {
+            None
+        } else {
+            Some(frequency)
+        }
+    }
+}
+
+pub struct Pulse {
+    sweep: Option<Sweep>,
+    duty: u8,
+    duty_position: u8,
+    frequency: u16,
+    timer: u32,
+    length: LengthCounter,
+    envelope: Envelope,
+    enabled: bool,
+}
+
+impl Pulse {
+    const MAX_FREQUENCY: u16 = 0x7FF;
+    const TRIGGER: u8 = 0x80;
+    const LENGTH_ENABLE: u8 = 0x40;
+
+    const DUTY_PATTERNS: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+    // channel 2 has no sweep unit, NR20 does not exist
+    pub fn new(sweep: bool) -> Pulse {
+        Pulse {
+            sweep: if sweep { Some(Sweep::new()) } else { None },
+            duty: 0,
+            duty_position: 0,
+            frequency: 0,
+            timer: 0,
+            length: LengthCounter::new(64),
+            envelope: Envelope::new(),
+            enabled: false,
+        }
+    }
+
+    // index 0 - 4 selects NRx0 - NRx4, write-only bits read as 1
+    pub fn read_register(&self, index: u16) -> u8 {
+        match index {
+            0 => match &self.sweep {
+                Some(sweep) => 0x80 | sweep.register,
+                None => 0xFF,
+            },
+            1 => 0x3F | self.duty << 6,
+            2 => self.envelope.read_register(),
+            3 => 0xFF,
+            4 => 0xBF | if self.length.is_enabled() { Pulse::LENGTH_ENABLE } else { 0 },
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_register(&mut self, index: u16, value: u8) {
+        match index {
+            0 => {
+                if let Some(sweep) = &mut self.sweep {
+                    sweep.register = value & 0x7F;
+                    if sweep.register & Sweep::NEGATE == 0 && sweep.negate_used {
+                        self.enabled = false;
+                    }
+                }
+            }
+            1 => {
+                self.duty = value >> 6;
+                self.length.load(value);
+            }
+            2 => {
+                self.envelope.write_register(value);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.frequency = (self.frequency & 0x700) | u16::from(value),
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | u16::from(value & 0x07) << 8;
+                self.length.set_enabled(value & Pulse::LENGTH_ENABLE != 0);
+                if value & Pulse::TRIGGER != 0 {
+                    self.trigger();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    // powering the APU off clears every register, on DMG the length counters are kept
+    pub fn power_off(&mut self, keep_length: bool) {
+        let length = if keep_length {
+            mem::replace(&mut self.length, LengthCounter::new(64))
+        } else {
+            LengthCounter::new(64)
+        };
+        *self = Pulse {
+            length,
+            ..Pulse::new(self.sweep.is_some())
+        };
+        self.length.set_enabled(false);
+    }
+
+    // digital amplitude from 0 to 15
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        let high = Pulse::DUTY_PATTERNS[usize::from(self.duty)] & (0x80 >> self.duty_position) != 0;
+        if high {
+            self.envelope.volume()
+        } else {
+            0
+        }
+    }
+
+    pub fn step(&mut self, cycles: u32) {
+        let mut cycles = cycles;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.duty_position = (self.duty_position + 1) % 8;
+        }
+        self.timer -= cycles;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    // clocked at 128 Hz by the frame sequencer
+    pub fn clock_sweep(&mut self) {
+        let sweep = match &mut self.sweep {
+            Some(sweep) => sweep,
+            None => return,
+        };
+        sweep.timer -= 1;
+        if sweep.timer > 0 {
+            return;
+        }
+        sweep.reload_timer();
+        if !sweep.enabled || sweep.period() == 0 {
+            return;
+        }
+
+        match sweep.calculate() {
+            Some(frequency) if sweep.shift() != 0 => {
+                sweep.shadow_frequency = frequency;
+                self.frequency = frequency;
+                // the new frequency is checked for overflow again right away
+                if sweep.calculate().is_none() {
+                    self.enabled = false;
+                }
+            }
+            Some(_) => (),
+            None => self.enabled = false,
+        }
+    }
+
+    // the frequency timer counts down (2048 - frequency) * 4 dots per duty step
+    fn period(&self) -> u32 {
+        (2048 - u32::from(self.frequency)) * 4
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.timer = self.period();
+        self.envelope.trigger();
+
+        if let Some(sweep) = &mut self.sweep {
+            sweep.shadow_frequency = self.frequency;
+            sweep.negate_used = false;
+            sweep.reload_timer();
+            sweep.enabled = sweep.period() != 0 || sweep.shift() != 0;
+            if sweep.shift() != 0 && sweep.calculate().is_none() {
+                self.enabled = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triggered_pulse(sweep: bool, nrx0: u8, frequency: u16) -> Pulse {
+        let mut pulse = Pulse::new(sweep);
+        pulse.write_register(0, nrx0);
+        pulse.write_register(1, 0x80);
+        pulse.write_register(2, 0xF0);
+        pulse.write_register(3, frequency as u8);
+        pulse.write_register(4, 0x80 | (frequency >> 8) as u8);
+        pulse
+    }
+
+    #[test]
+    fn pulse_duty_test() {
+        let period = (2048 - 0x700) * 4;
+        for (duty, pattern) in Pulse::DUTY_PATTERNS.iter().enumerate() {
+            let mut pulse = triggered_pulse(false, 0x00, 0x700);
+            pulse.write_register(1, (duty as u8) << 6);
+            let mut output = 0;
+            for _ in 0..8 {
+                pulse.step(period);
+                output = output << 1 | (pulse.output() == 15) as u8;
+            }
+            // the first step after the trigger moves to the second duty position
+            assert_eq!(output, pattern.rotate_left(1), "duty {}", duty);
+        }
+    }
+
+    #[test]
+    fn pulse_envelope_test() {
+        let mut pulse = triggered_pulse(false, 0x00, 0x700);
+        pulse.write_register(2, 0x52);
+        pulse.write_register(4, 0x87);
+        assert_eq!(pulse.envelope.volume(), 5);
+        pulse.clock_envelope();
+        assert_eq!(pulse.envelope.volume(), 5);
+        pulse.clock_envelope();
+        assert_eq!(pulse.envelope.volume(), 4);
+        for _ in 0..20 {
+            pulse.clock_envelope();
+        }
+        assert_eq!(pulse.envelope.volume(), 0);
+        assert!(pulse.enabled);
+
+        pulse.write_register(2, 0xE9);
+        pulse.write_register(4, 0x87);
+        for _ in 0..4 {
+            pulse.clock_envelope();
+        }
+        assert_eq!(pulse.envelope.volume(), 15);
+
+        // clearing the DAC bits turns the channel off
+        pulse.write_register(2, 0x07);
+        assert!(!pulse.enabled);
+        pulse.write_register(4, 0x87);
+        assert!(!pulse.enabled);
+    }
+
+    #[test]
+    fn pulse_length_test() {
+        let mut pulse = triggered_pulse(false, 0x00, 0x700);
+        pulse.write_register(1, 0x3E);
+        pulse.write_register(4, 0x47);
+        assert!(pulse.enabled);
+        pulse.clock_length();
+        assert!(pulse.enabled);
+        pulse.clock_length();
+        assert!(!pulse.enabled);
+        assert_eq!(pulse.read_register(4), 0xFF);
+
+        // an expired counter is reloaded with 64 on trigger
+        pulse.write_register(4, 0xC7);
+        for _ in 0..63 {
+            pulse.clock_length();
+        }
+        assert!(pulse.enabled);
+        pulse.clock_length();
+        assert!(!pulse.enabled);
+
+        // without the enable bit the counter is frozen
+        pulse.write_register(1, 0x3F);
+        pulse.write_register(4, 0x87);
+        for _ in 0..100 {
+            pulse.clock_length();
+        }
+        assert!(pulse.enabled);
+        assert_eq!(pulse.read_register(4), 0xBF);
+    }
+
+    #[test]
+    fn pulse_sweep_test() {
+        // period 1, shift 1: the frequency grows by half every sweep clock
+        let mut pulse = triggered_pulse(true, 0x11, 0x200);
+        pulse.clock_sweep();
+        assert_eq!(pulse.frequency, 0x300);
+        pulse.clock_sweep();
+        assert_eq!(pulse.frequency, 0x480);
+        assert!(pulse.enabled);
+        // 0x6C0 + 0x360 overflows in the second check
+        pulse.clock_sweep();
+        assert_eq!(pulse.frequency, 0x6C0);
+        assert!(!pulse.enabled);
+
+        // an overflow is detected on trigger when the shift is not 0
+        let pulse = triggered_pulse(true, 0x01, 0x7F0);
+        assert!(!pulse.enabled);
+
+        // negate mode decreases the frequency
+        let mut pulse = triggered_pulse(true, 0x2A, 0x400);
+        pulse.clock_sweep();
+        assert_eq!(pulse.frequency, 0x400);
+        pulse.clock_sweep();
+        assert_eq!(pulse.frequency, 0x300);
+        assert_eq!(pulse.read_register(0), 0xAA);
+
+        // leaving negate mode after it was used disables the channel
+        pulse.write_register(0, 0x22);
+        assert!(!pulse.enabled);
+    }
+
+    #[test]
+    fn pulse_registers_test() {
+        let mut pulse = Pulse::new(false);
+        pulse.write_register(0, 0x12);
+        pulse.write_register(1, 0x9F);
+        pulse.write_register(2, 0xA5);
+        pulse.write_register(3, 0x12);
+        assert_eq!(pulse.read_register(0), 0xFF);
+        assert_eq!(pulse.read_register(1), 0xBF);
+        assert_eq!(pulse.read_register(2), 0xA5);
+        assert_eq!(pulse.read_register(3), 0xFF);
+        assert_eq!(pulse.read_register(4), 0xBF);
+    }
+}
@@ -0,0 +1,306 @@
+use super::length::LengthCounter;
+use std::mem;
+
+// channel 3 plays 32 4-bit samples from wave RAM
+pub struct Wave {
+    ram: [u8; Wave::RAM_SIZE],
+    dac_enabled: bool,
+    volume_code: u8,
+    frequency: u16,
+    timer: u32,
+    position: u8,
+    sample: u8,
+    // DMG only lets the CPU into wave RAM in the M-cycle the channel fetches from it
+    dots_since_fetch: u32,
+    length: LengthCounter,
+    enabled: bool,
+}
+
+impl Wave {
+    pub const RAM_SIZE: usize = 0x10;
+
+    const DAC_ENABLE: u8 = 0x80;
+    const TRIGGER: u8 = 0x80;
+    const LENGTH_ENABLE: u8 = 0x40;
+    // the first sample is fetched 3 APU cycles later than the frequency alone says
+    const TRIGGER_DELAY_DOTS: u32 = 6;
+    const FETCH_WINDOW_DOTS: u32 = 4;
+
+    pub fn new() -> Wave {
+        Wave {
+            ram: [0; Wave::RAM_SIZE],
+            dac_enabled: false,
+            volume_code: 0,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            sample: 0,
+            dots_since_fetch: u32::MAX,
+            length: LengthCounter::new(256),
+            enabled: false,
+        }
+    }
+
+    // index 0 - 4 selects NR30 - NR34, write-only bits read as 1
+    pub fn read_register(&self, index: u16) -> u8 {
+        match index {
+            0 => 0x7F | if self.dac_enabled { Wave::DAC_ENABLE } else { 0 },
+            1 => 0xFF,
+            2 => 0x9F | self.volume_code << 5,
+            3 => 0xFF,
+            4 => 0xBF | if self.length.is_enabled() { Wave::LENGTH_ENABLE } else { 0 },
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_register(&mut self, index: u16, value: u8) {
+        match index {
+            0 => {
+                self.dac_enabled = value & Wave::DAC_ENABLE != 0;
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
+            }
+            1 => self.length.load(value),
+            2 => self.volume_code = (value >> 5) & 0x03,
+            3 => self.frequency = (self.frequency & 0x700) | u16::from(value),
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | u16::from(value & 0x07) << 8;
+                self.length.set_enabled(value & Wave::LENGTH_ENABLE != 0);
+                if value & Wave::TRIGGER != 0 {
+                    self.trigger();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // while playing, the CPU can only reach the byte being played and only right as it is fetched
+    pub fn read_ram(&self, offset: u16) -> u8 {
+        if !self.enabled {
+            return self.ram[usize::from(offset)];
+        }
+        if self.dots_since_fetch < Wave::FETCH_WINDOW_DOTS {
+            self.ram[usize::from(self.position / 2)]
+        } else {
+            0xFF
+        }
+    }
+
+    pub fn write_ram(&mut self, offset: u16, value: u8) {
+        if !self.enabled {
+            self.ram[usize::from(offset)] = value;
+        } else if self.dots_since_fetch < Wave::FETCH_WINDOW_DOTS {
+            self.ram[usize::from(self.position / 2)] = value;
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    // wave RAM and, on DMG, the length counter survive the APU being powered off
+    pub fn power_off(&mut self, keep_length: bool) {
+        let length = if keep_length {
+            mem::replace(&mut self.length, LengthCounter::new(256))
+        } else {
+            LengthCounter::new(256)
+        };
+        *self = Wave {
+            ram: self.ram,
+            length,
+            ..Wave::new()
+        };
+        self.length.set_enabled(false);
+    }
+
+    // digital amplitude from 0 to 15, volume codes 0 - 3 shift the sample right by 4, 0, 1 and 2
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        match self.volume_code {
+            0 => 0,
+            code => self.sample >> (code - 1),
+        }
+    }
+
+    pub fn step(&mut self, cycles: u32) {
+        if !self.enabled {
+            return;
+        }
+        let mut cycles = cycles;
+        self.dots_since_fetch = self.dots_since_fetch.saturating_add(cycles);
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.position = (self.position + 1) % 32;
+            let byte = self.ram[usize::from(self.position / 2)];
+            // the high nibble is played first
+            self.sample = if self.position & 0x01 == 0 { byte >> 4 } else { byte & 0x0F };
+            self.dots_since_fetch = cycles;
+        }
+        self.timer -= cycles;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    // the frequency timer counts down (2048 - frequency) * 2 dots per sample
+    fn period(&self) -> u32 {
+        (2048 - u32::from(self.frequency)) * 2
+    }
+
+    fn trigger(&mut self) {
+        // on DMG retriggering right as a sample is fetched corrupts the start of wave RAM
+        if self.enabled && self.timer <= 2 {
+            let offset = usize::from((self.position + 1) % 32 / 2);
+            if offset < 4 {
+                self.ram[0] = self.ram[offset];
+            } else {
+                let block = offset & !0x03;
+                self.ram.copy_within(block..block + 4, 0);
+            }
+        }
+
+        self.enabled = self.dac_enabled;
+        self.length.trigger();
+        self.timer = self.period() + Wave::TRIGGER_DELAY_DOTS;
+        // the sample buffer is not refilled, playback resumes at the second sample
+        self.position = 0;
+        self.dots_since_fetch = u32::MAX;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // frequency 0x7FC fetches a sample every 8 dots
+    fn playing_wave(ram: [u8; Wave::RAM_SIZE]) -> Wave {
+        let mut wave = Wave::new();
+        for (offset, value) in ram.iter().enumerate() {
+            wave.write_ram(offset as u16, *value);
+        }
+        wave.write_register(0, 0x80);
+        wave.write_register(2, 0x20);
+        wave.write_register(3, 0xFC);
+        wave.write_register(4, 0x87);
+        wave
+    }
+
+    fn counting_ram() -> [u8; Wave::RAM_SIZE] {
+        let mut ram = [0; Wave::RAM_SIZE];
+        for (offset, value) in ram.iter_mut().enumerate() {
+            let sample = (offset as u8 * 2) & 0x0F;
+            *value = sample << 4 | (sample + 1);
+        }
+        ram
+    }
+
+    #[test]
+    fn wave_playback_test() {
+        let mut wave = playing_wave(counting_ram());
+        // the first fetch comes after the trigger delay and skips sample 0
+        wave.step(8 + 5);
+        assert_eq!(wave.output(), 0);
+        wave.step(1);
+        assert_eq!(wave.output(), 1);
+        wave.step(8);
+        assert_eq!(wave.output(), 2);
+        wave.step(8 * 30);
+        assert_eq!(wave.output(), 0);
+        wave.step(8);
+        assert_eq!(wave.output(), 1);
+
+        // volume codes shift the sample right
+        wave.step(8 * 14);
+        assert_eq!(wave.output(), 15);
+        for (code, output) in [(0, 0), (1, 15), (2, 7), (3, 3)].iter() {
+            wave.write_register(2, code << 5);
+            assert_eq!(wave.output(), *output);
+            assert_eq!(wave.read_register(2), 0x9F | code << 5);
+        }
+
+        // turning the DAC off stops the channel
+        wave.write_register(0, 0x00);
+        assert!(!wave.enabled);
+        assert_eq!(wave.read_register(0), 0x7F);
+        wave.write_register(4, 0x80);
+        assert!(!wave.enabled);
+    }
+
+    #[test]
+    fn wave_length_test() {
+        let mut wave = playing_wave(counting_ram());
+        wave.write_register(1, 0x00);
+        wave.write_register(4, 0xC7);
+        for _ in 0..255 {
+            wave.clock_length();
+        }
+        assert!(wave.enabled);
+        wave.clock_length();
+        assert!(!wave.enabled);
+        assert_eq!(wave.read_register(4), 0xFF);
+
+        wave.write_register(1, 0xFE);
+        wave.write_register(4, 0xC7);
+        wave.clock_length();
+        assert!(wave.enabled);
+        wave.clock_length();
+        assert!(!wave.enabled);
+    }
+
+    #[test]
+    fn wave_ram_access_test() {
+        let mut wave = playing_wave(counting_ram());
+        assert_eq!(wave.read_ram(0x05), 0xFF);
+
+        // only the byte being played is reachable, in the M-cycle it is fetched
+        wave.step(14 + 8 * 4);
+        assert_eq!(wave.read_ram(0x0F), 0x45);
+        wave.write_ram(0x0F, 0xAB);
+        wave.step(4);
+        assert_eq!(wave.read_ram(0x02), 0xFF);
+        wave.write_ram(0x02, 0xCD);
+
+        wave.write_register(0, 0x00);
+        assert_eq!(wave.read_ram(0x02), 0xAB);
+        assert_eq!(wave.read_ram(0x0F), 0xEF);
+    }
+
+    #[test]
+    fn wave_retrigger_corruption_test() {
+        // retriggering right before the fetch of sample 10 copies bytes 4 - 7 over bytes 0 - 3
+        let mut wave = playing_wave(counting_ram());
+        wave.step(14 + 8 * 8 + 6);
+        wave.write_register(4, 0x87);
+        wave.write_register(0, 0x00);
+        assert_eq!(wave.read_ram(0x00), 0x89);
+        assert_eq!(wave.read_ram(0x03), 0xEF);
+        assert_eq!(wave.read_ram(0x04), 0x89);
+
+        // within the first 4 bytes only byte 0 is overwritten
+        let mut wave = playing_wave(counting_ram());
+        wave.step(14 + 6);
+        wave.write_register(4, 0x87);
+        wave.write_register(0, 0x00);
+        assert_eq!(wave.read_ram(0x00), 0x23);
+        assert_eq!(wave.read_ram(0x01), 0x23);
+        assert_eq!(wave.read_ram(0x02), 0x45);
+
+        // away from a fetch nothing happens
+        let mut wave = playing_wave(counting_ram());
+        wave.step(14 + 8 * 8 + 2);
+        wave.write_register(4, 0x87);
+        wave.write_register(0, 0x00);
+        assert_eq!(wave.read_ram(0x00), 0x01);
+    }
+}
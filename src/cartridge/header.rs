@@ -0,0 +1,200 @@
+use super::CartridgeError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mbc {
+    RomOnly,
+    Mbc1,
+    Mbc2,
+    Mbc3,
+    Mbc5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CartridgeType {
+    pub mbc: Mbc,
+    pub ram: bool,
+    pub battery: bool,
+    pub timer: bool,
+    pub rumble: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CgbFlag {
+    DmgOnly,
+    CgbSupported,
+    CgbOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Destination {
+    Japanese,
+    NonJapanese,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub title: String,
+    pub manufacturer_code: String,
+    pub cgb_flag: CgbFlag,
+    pub new_licensee_code: String,
+    pub sgb_flag: bool,
+    pub cartridge_type: CartridgeType,
+    pub rom_size: usize,
+    pub ram_size: usize,
+    pub destination: Destination,
+    pub old_licensee_code: u8,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl Header {
+    pub const START: usize = 0x0100;
+    pub const END: usize = 0x0150;
+    pub const LOGO: usize = 0x0104;
+
+    #[rustfmt::skip]
+    pub const NINTENDO_LOGO: [u8; 48] = [
+        0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+        0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+        0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+    ];
+
+    const TITLE: usize = 0x0134;
+    const MANUFACTURER_CODE: usize = 0x013F;
+    const CGB_FLAG: usize = 0x0143;
+    const NEW_LICENSEE_CODE: usize = 0x0144;
+    const SGB_FLAG: usize = 0x0146;
+    const CARTRIDGE_TYPE: usize = 0x0147;
+    const ROM_SIZE: usize = 0x0148;
+    const RAM_SIZE: usize = 0x0149;
+    const DESTINATION_CODE: usize = 0x014A;
+    const OLD_LICENSEE_CODE: usize = 0x014B;
+    const VERSION: usize = 0x014C;
+    const HEADER_CHECKSUM: usize = 0x014D;
+    const GLOBAL_CHECKSUM: usize = 0x014E;
+
+    pub fn parse(rom: &[u8]) -> Result<Header, CartridgeError> {
+        if rom.len() < Header::END {
+            return Err(CartridgeError::Truncated {
+                expected: Header::END,
+                actual: rom.len(),
+            });
+        }
+
+        let cgb_flag = match rom[Header::CGB_FLAG] {
+            0x80 => CgbFlag::CgbSupported,
+            0xC0 => CgbFlag::CgbOnly,
+            _ => CgbFlag::DmgOnly,
+        };
+        // in CGB era cartridges the last bytes of the title area hold the manufacturer code and CGB flag
+        let (title, manufacturer_code) = match cgb_flag {
+            CgbFlag::DmgOnly => (Header::parse_string(&rom[Header::TITLE..Header::CGB_FLAG + 1]), String::new()),
+            _ => (
+                Header::parse_string(&rom[Header::TITLE..Header::MANUFACTURER_CODE]),
+                Header::parse_string(&rom[Header::MANUFACTURER_CODE..Header::CGB_FLAG]),
+            ),
+        };
+
+        let header = Header {
+            title,
+            manufacturer_code,
+            cgb_flag,
+            new_licensee_code: Header::parse_string(&rom[Header::NEW_LICENSEE_CODE..Header::SGB_FLAG]),
+            sgb_flag: rom[Header::SGB_FLAG] == 0x03,
+            cartridge_type: CartridgeType::parse(rom[Header::CARTRIDGE_TYPE])?,
+            rom_size: Header::parse_rom_size(rom[Header::ROM_SIZE])?,
+            ram_size: Header::parse_ram_size(rom[Header::RAM_SIZE])?,
+            destination: match rom[Header::DESTINATION_CODE] {
+                0x00 => Destination::Japanese,
+                _ => Destination::NonJapanese,
+            },
+            old_licensee_code: rom[Header::OLD_LICENSEE_CODE],
+            version: rom[Header::VERSION],
+            header_checksum: rom[Header::HEADER_CHECKSUM],
+            global_checksum: u16::from(rom[Header::GLOBAL_CHECKSUM]) << 8 | u16::from(rom[Header::GLOBAL_CHECKSUM + 1]),
+        };
+
+        let header_checksum = Header::compute_header_checksum(rom);
+        if header_checksum != header.header_checksum {
+            return Err(CartridgeError::HeaderChecksumMismatch {
+                expected: header.header_checksum,
+                computed: header_checksum,
+            });
+        }
+
+        Ok(header)
+    }
+
+    pub fn compute_header_checksum(rom: &[u8]) -> u8 {
+        rom[Header::TITLE..Header::HEADER_CHECKSUM]
+            .iter()
+            .fold(0u8, |checksum, byte| checksum.wrapping_sub(*byte).wrapping_sub(1))
+    }
+
+    pub fn compute_global_checksum(rom: &[u8]) -> u16 {
+        rom.iter()
+            .enumerate()
+            .filter(|(address, _)| *address != Header::GLOBAL_CHECKSUM && *address != Header::GLOBAL_CHECKSUM + 1)
+            .fold(0u16, |checksum, (_, byte)| checksum.wrapping_add(u16::from(*byte)))
+    }
+
+    fn parse_string(bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .take_while(|byte| **byte != 0x00)
+            .map(|byte| *byte as char)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    fn parse_rom_size(code: u8) -> Result<usize, CartridgeError> {
+        match code {
+            0x00..=0x08 => Ok(0x8000 << code),
+            _ => Err(CartridgeError::InvalidRomSize(code)),
+        }
+    }
+
+    fn parse_ram_size(code: u8) -> Result<usize, CartridgeError> {
+        match code {
+            0x00 => Ok(0),
+            0x01 => Ok(0x800),
+            0x02 => Ok(0x2000),
+            0x03 => Ok(0x8000),
+            0x04 => Ok(0x20000),
+            0x05 => Ok(0x10000),
+            _ => Err(CartridgeError::InvalidRamSize(code)),
+        }
+    }
+}
+
+impl CartridgeType {
+    #[rustfmt::skip]
+    fn parse(code: u8) -> Result<CartridgeType, CartridgeError> {
+        let (mbc, ram, battery, timer, rumble) = match code {
+            0x00 => (Mbc::RomOnly, false, false, false, false),
+            0x01 => (Mbc::Mbc1, false, false, false, false),
+            0x02 => (Mbc::Mbc1, true, false, false, false),
+            0x03 => (Mbc::Mbc1, true, true, false, false),
+            0x05 => (Mbc::Mbc2, false, false, false, false),
+            0x06 => (Mbc::Mbc2, false, true, false, false),
+            0x08 => (Mbc::RomOnly, true, false, false, false),
+            0x09 => (Mbc::RomOnly, true, true, false, false),
+            0x0F => (Mbc::Mbc3, false, true, true, false),
+            0x10 => (Mbc::Mbc3, true, true, true, false),
+            0x11 => (Mbc::Mbc3, false, false, false, false),
+            0x12 => (Mbc::Mbc3, true, false, false, false),
+            0x13 => (Mbc::Mbc3, true, true, false, false),
+            0x19 => (Mbc::Mbc5, false, false, false, false),
+            0x1A => (Mbc::Mbc5, true, false, false, false),
+            0x1B => (Mbc::Mbc5, true, true, false, false),
+            0x1C => (Mbc::Mbc5, false, false, false, true),
+            0x1D => (Mbc::Mbc5, true, false, false, true),
+            0x1E => (Mbc::Mbc5, true, true, false, true),
+            _ => return Err(CartridgeError::UnsupportedCartridgeType(code)),
+        };
+
+        Ok(CartridgeType { mbc, ram, battery, timer, rumble })
+    }
+}
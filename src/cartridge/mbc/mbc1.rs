@@ -0,0 +1,199 @@
+use super::{ram_offset, rom_offset, store, MemoryBankController};
+
+pub struct Mbc1 {
+    ram_enabled: bool,
+    rom_bank: u8,
+    upper_bank: u8,
+    advanced_banking_mode: bool,
+    // multicarts wire only 4 bits of the ROM bank register, the upper bank selects a whole game
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(multicart: bool) -> Mbc1 {
+        Mbc1 {
+            ram_enabled: false,
+            rom_bank: 0x01,
+            upper_bank: 0x00,
+            advanced_banking_mode: false,
+            multicart,
+        }
+    }
+
+    fn upper_bank_shift(&self) -> usize {
+        if self.multicart {
+            4
+        } else {
+            5
+        }
+    }
+
+    fn lower_rom_bank(&self) -> usize {
+        if self.advanced_banking_mode {
+            usize::from(self.upper_bank) << self.upper_bank_shift()
+        } else {
+            0
+        }
+    }
+
+    fn upper_rom_bank(&self) -> usize {
+        let rom_bank_mask = if self.multicart { 0x0F } else { 0x1F };
+        usize::from(self.upper_bank) << self.upper_bank_shift() | usize::from(self.rom_bank & rom_bank_mask)
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.advanced_banking_mode {
+            usize::from(self.upper_bank)
+        } else {
+            0
+        }
+    }
+}
+
+impl MemoryBankController for Mbc1 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => rom[rom_offset(rom, self.lower_rom_bank(), address)],
+            _ => rom[rom_offset(rom, self.upper_rom_bank(), address)],
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // bank 0 cannot be selected, the check ignores the upper bank bits
+                self.rom_bank = match value & 0x1F {
+                    0x00 => 0x01,
+                    bank => bank,
+                };
+            }
+            0x4000..=0x5FFF => self.upper_bank = value & 0x03,
+            _ => self.advanced_banking_mode = value & 0x01 != 0,
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled || ram.is_empty() {
+            return 0xFF;
+        }
+        ram[ram_offset(ram, self.ram_bank(), address)]
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) -> bool {
+        self.ram_enabled && !ram.is_empty() && store(&mut ram[ram_offset(ram, self.ram_bank(), address)], value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{RAM_BANK_SIZE, ROM_BANK_SIZE};
+    use super::*;
+
+    // every bank starts with its own number
+    fn banked_rom(bank_count: usize) -> Vec<u8> {
+        let mut rom = vec![0; bank_count * ROM_BANK_SIZE];
+        for bank in 0..bank_count {
+            rom[bank * ROM_BANK_SIZE] = bank as u8;
+        }
+        rom
+    }
+
+    #[test]
+    fn mbc1_rom_banking_test() {
+        let rom = banked_rom(128);
+        let mut mbc = Mbc1::new(false);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+
+        mbc.write_rom(0x2000, 0x05);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x05);
+        // only 5 bits are used
+        mbc.write_rom(0x3FFF, 0xE7);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x07);
+
+        // bank 0 is mapped to bank 1
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+        // the same applies to 0x20, 0x40 and 0x60
+        mbc.write_rom(0x4000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x21);
+        mbc.write_rom(0x4000, 0x03);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x61);
+        // but not when the ROM is too small to use all 5 bits
+        let small_rom = banked_rom(16);
+        mbc.write_rom(0x4000, 0x00);
+        mbc.write_rom(0x2000, 0x10);
+        assert_eq!(mbc.read_rom(&small_rom, 0x4000), 0x00);
+
+        // in simple banking mode 0x0000 - 0x3FFF is always bank 0
+        mbc.write_rom(0x2000, 0x02);
+        mbc.write_rom(0x4000, 0x02);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x42);
+        // in advanced banking mode it is switched by the upper bank register
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x40);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x42);
+
+        // banks wrap around the ROM size
+        let rom = banked_rom(4);
+        let mut mbc = Mbc1::new(false);
+        mbc.write_rom(0x2000, 0x06);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x02);
+    }
+
+    #[test]
+    fn mbc1_ram_test() {
+        let mut ram = vec![0; 4 * RAM_BANK_SIZE];
+        let mut mbc = Mbc1::new(false);
+
+        // RAM is disabled by default
+        mbc.write_ram(&mut ram, 0xA000, 0x12);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0xFF);
+        assert_eq!(ram[0], 0x00);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(&mut ram, 0xA000, 0x12);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0x12);
+
+        // RAM banking requires advanced banking mode
+        mbc.write_rom(0x4000, 0x02);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0x12);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0x00);
+        mbc.write_ram(&mut ram, 0xBFFF, 0x34);
+        assert_eq!(ram[3 * RAM_BANK_SIZE - 1], 0x34);
+
+        // any value other than 0x0A in the lower nibble disables RAM
+        mbc.write_rom(0x1FFF, 0x1B);
+        assert_eq!(mbc.read_ram(&ram, 0xBFFF), 0xFF);
+        mbc.write_rom(0x1FFF, 0xFA);
+        assert_eq!(mbc.read_ram(&ram, 0xBFFF), 0x34);
+
+        // missing RAM reads as 0xFF
+        let mut no_ram = vec![];
+        mbc.write_ram(&mut no_ram, 0xA000, 0x12);
+        assert_eq!(mbc.read_ram(&no_ram, 0xA000), 0xFF);
+    }
+
+    #[test]
+    fn mbc1_multicart_test() {
+        let rom = banked_rom(64);
+        let mut mbc = Mbc1::new(true);
+
+        mbc.write_rom(0x2000, 0x1F);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x0F);
+        mbc.write_rom(0x4000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x1F);
+
+        // each game occupies 16 banks, selected in the lower area in advanced banking mode
+        mbc.write_rom(0x6000, 0x01);
+        mbc.write_rom(0x4000, 0x02);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x20);
+        // bank 0x10 does not map to 0x11, as the zero check uses all 5 bits
+        mbc.write_rom(0x2000, 0x10);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x20);
+    }
+}
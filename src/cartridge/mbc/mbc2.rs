@@ -0,0 +1,110 @@
+use super::{rom_offset, store, MemoryBankController};
+
+pub struct Mbc2 {
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    // 512 half-bytes of RAM are built into the controller
+    pub const RAM_SIZE: usize = 0x200;
+
+    pub fn new() -> Mbc2 {
+        Mbc2 {
+            ram_enabled: false,
+            rom_bank: 0x01,
+        }
+    }
+}
+
+impl MemoryBankController for Mbc2 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => rom[rom_offset(rom, 0, address)],
+            _ => rom[rom_offset(rom, usize::from(self.rom_bank), address)],
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        // registers are selected by bit 8 of the address
+        match address {
+            0x0000..=0x3FFF if address & 0x0100 == 0 => self.ram_enabled = value & 0x0F == 0x0A,
+            0x0000..=0x3FFF => {
+                self.rom_bank = match value & 0x0F {
+                    0x00 => 0x01,
+                    bank => bank,
+                };
+            }
+            _ => (),
+        }
+    }
+
+    // only the lower nibble is stored, 0xA000 - 0xA1FF is echoed through the whole RAM area
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        0xF0 | ram[address as usize & (Mbc2::RAM_SIZE - 1)]
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) -> bool {
+        self.ram_enabled && store(&mut ram[address as usize & (Mbc2::RAM_SIZE - 1)], value & 0x0F)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ROM_BANK_SIZE;
+    use super::*;
+
+    #[test]
+    fn mbc2_rom_banking_test() {
+        let mut rom = vec![0; 16 * ROM_BANK_SIZE];
+        for bank in 0..16 {
+            rom[bank * ROM_BANK_SIZE] = bank as u8;
+        }
+        let mut mbc = Mbc2::new();
+
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+        mbc.write_rom(0x2100, 0x0F);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x0F);
+        mbc.write_rom(0x0100, 0xF5);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x05);
+        mbc.write_rom(0x3FFF, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+
+        // with bit 8 cleared the write goes to the RAM enable register
+        mbc.write_rom(0x2000, 0x03);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+        // writes above 0x3FFF are ignored
+        mbc.write_rom(0x4100, 0x03);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+    }
+
+    #[test]
+    fn mbc2_ram_test() {
+        let mut ram = vec![0; Mbc2::RAM_SIZE];
+        let mut mbc = Mbc2::new();
+
+        mbc.write_ram(&mut ram, 0xA000, 0x0C);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0xFF);
+
+        // bit 8 must be cleared to access the RAM enable register
+        mbc.write_rom(0x0100, 0x0A);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0xFF);
+        mbc.write_rom(0x3EFF, 0x0A);
+        mbc.write_ram(&mut ram, 0xA000, 0xAC);
+        assert_eq!(ram[0], 0x0C);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0xFC);
+
+        // RAM is echoed every 512 bytes
+        assert_eq!(mbc.read_ram(&ram, 0xA200), 0xFC);
+        assert_eq!(mbc.read_ram(&ram, 0xBE00), 0xFC);
+        mbc.write_ram(&mut ram, 0xBFFF, 0x05);
+        assert_eq!(mbc.read_ram(&ram, 0xA1FF), 0xF5);
+
+        mbc.write_rom(0x0000, 0x00);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0xFF);
+    }
+}
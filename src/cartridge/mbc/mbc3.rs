@@ -0,0 +1,519 @@
+use super::{ram_offset, rom_offset, store, MemoryBankController};
+
+pub struct Mbc3 {
+    ram_and_rtc_enabled: bool,
+    rom_bank: u8,
+    ram_bank_or_rtc_register: u8,
+    latch_value: u8,
+    rtc: Option<RealTimeClock>,
+}
+
+impl Mbc3 {
+    pub fn new(timer: bool) -> Mbc3 {
+        Mbc3 {
+            ram_and_rtc_enabled: false,
+            rom_bank: 0x01,
+            ram_bank_or_rtc_register: 0x00,
+            latch_value: 0xFF,
+            rtc: if timer { Some(RealTimeClock::new()) } else { None },
+        }
+    }
+}
+
+impl MemoryBankController for Mbc3 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => rom[rom_offset(rom, 0, address)],
+            _ => rom[rom_offset(rom, usize::from(self.rom_bank), address)],
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_and_rtc_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                self.rom_bank = match value & 0x7F {
+                    0x00 => 0x01,
+                    bank => bank,
+                };
+            }
+            0x4000..=0x5FFF => self.ram_bank_or_rtc_register = value & 0x0F,
+            _ => {
+                // registers are latched by writing 0x00 followed by 0x01
+                if self.latch_value == 0x00 && value == 0x01 {
+                    if let Some(rtc) = self.rtc.as_mut() {
+                        rtc.latch();
+                    }
+                }
+                self.latch_value = value;
+            }
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_and_rtc_enabled {
+            return 0xFF;
+        }
+        match (self.ram_bank_or_rtc_register, self.rtc.as_ref()) {
+            (0x00..=0x07, _) if !ram.is_empty() => {
+                ram[ram_offset(ram, usize::from(self.ram_bank_or_rtc_register), address)]
+            }
+            (0x08..=0x0C, Some(rtc)) => rtc.read_register(self.ram_bank_or_rtc_register),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) -> bool {
+        if !self.ram_and_rtc_enabled {
+            return false;
+        }
+        match (self.ram_bank_or_rtc_register, self.rtc.as_mut()) {
+            (0x00..=0x07, _) if !ram.is_empty() => {
+                store(&mut ram[ram_offset(ram, usize::from(self.ram_bank_or_rtc_register), address)], value)
+            }
+            (0x08..=0x0C, Some(rtc)) => {
+                rtc.write_register(self.ram_bank_or_rtc_register, value);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn step(&mut self, cycles: u32) {
+        if let Some(rtc) = self.rtc.as_mut() {
+            rtc.step(cycles);
+        }
+    }
+
+    fn set_rtc_time(&mut self, timestamp: u64) {
+        if let Some(rtc) = self.rtc.as_mut() {
+            rtc.set_time(timestamp);
+        }
+    }
+
+    fn save_rtc(&self, timestamp: u64) -> Vec<u8> {
+        match self.rtc.as_ref() {
+            Some(rtc) => rtc.save(timestamp),
+            None => Vec::new(),
+        }
+    }
+
+    fn load_rtc(&mut self, save: &[u8], timestamp: u64) {
+        if let Some(rtc) = self.rtc.as_mut() {
+            rtc.load(save, timestamp);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RtcRegisters {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halted: bool,
+    day_carry: bool,
+}
+
+impl RtcRegisters {
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            RealTimeClock::SECONDS_REGISTER => self.seconds,
+            RealTimeClock::MINUTES_REGISTER => self.minutes,
+            RealTimeClock::HOURS_REGISTER => self.hours,
+            RealTimeClock::DAYS_LOW_REGISTER => (self.days & 0xFF) as u8,
+            RealTimeClock::DAYS_HIGH_REGISTER => {
+                u8::from(self.day_carry) << 7 | u8::from(self.halted) << 6 | (self.days >> 8) as u8
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        match register {
+            RealTimeClock::SECONDS_REGISTER => self.seconds = value & 0x3F,
+            RealTimeClock::MINUTES_REGISTER => self.minutes = value & 0x3F,
+            RealTimeClock::HOURS_REGISTER => self.hours = value & 0x1F,
+            RealTimeClock::DAYS_LOW_REGISTER => self.days = (self.days & 0x100) | u16::from(value),
+            RealTimeClock::DAYS_HIGH_REGISTER => {
+                self.days = (self.days & 0xFF) | u16::from(value & 0x01) << 8;
+                self.halted = value & 0x40 != 0;
+                self.day_carry = value & 0x80 != 0;
+            }
+            _ => (),
+        }
+    }
+}
+
+pub struct RealTimeClock {
+    live: RtcRegisters,
+    latched: RtcRegisters,
+    cycles: u32,
+}
+
+impl RealTimeClock {
+    const CYCLES_PER_SECOND: u32 = 4_194_304;
+
+    const SECONDS_REGISTER: u8 = 0x08;
+    const MINUTES_REGISTER: u8 = 0x09;
+    const HOURS_REGISTER: u8 = 0x0A;
+    const DAYS_LOW_REGISTER: u8 = 0x0B;
+    const DAYS_HIGH_REGISTER: u8 = 0x0C;
+
+    // the live and latched registers as 32-bit words followed by a 64-bit Unix timestamp, all little
+    // endian, as written by most emulators, older saves have a 32-bit timestamp
+    const SAVE_SIZE: usize = 48;
+    const OLD_SAVE_SIZE: usize = 44;
+
+    fn new() -> RealTimeClock {
+        let registers = RtcRegisters {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halted: false,
+            day_carry: false,
+        };
+        RealTimeClock {
+            live: registers,
+            latched: registers,
+            cycles: 0,
+        }
+    }
+
+    fn latch(&mut self) {
+        self.latched = self.live;
+    }
+
+    fn read_register(&self, register: u8) -> u8 {
+        self.latched.read(register)
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) {
+        if register == RealTimeClock::SECONDS_REGISTER {
+            self.cycles = 0;
+        }
+        self.live.write(register, value);
+    }
+
+    fn step(&mut self, cycles: u32) {
+        if self.live.halted {
+            return;
+        }
+        self.cycles += cycles;
+        while self.cycles >= RealTimeClock::CYCLES_PER_SECOND {
+            self.cycles -= RealTimeClock::CYCLES_PER_SECOND;
+            self.tick();
+        }
+    }
+
+    // out of range values keep counting up to the register width before wrapping to 0 without a carry
+    fn tick(&mut self) {
+        if self.live.seconds != 59 {
+            self.live.seconds = (self.live.seconds + 1) & 0x3F;
+            return;
+        }
+        self.live.seconds = 0;
+
+        if self.live.minutes != 59 {
+            self.live.minutes = (self.live.minutes + 1) & 0x3F;
+            return;
+        }
+        self.live.minutes = 0;
+
+        if self.live.hours != 23 {
+            self.live.hours = (self.live.hours + 1) & 0x1F;
+            return;
+        }
+        self.live.hours = 0;
+
+        if self.live.days == 0x1FF {
+            self.live.days = 0;
+            self.live.day_carry = true;
+        } else {
+            self.live.days += 1;
+        }
+    }
+
+    // days since the epoch would overflow the 9-bit day counter, games read the carry as a clock error
+    fn set_time(&mut self, timestamp: u64) {
+        let seconds = timestamp % 86400;
+        self.live.seconds = (seconds % 60) as u8;
+        self.live.minutes = (seconds / 60 % 60) as u8;
+        self.live.hours = (seconds / 3600) as u8;
+        self.live.days = 0;
+        self.live.day_carry = false;
+        self.cycles = 0;
+    }
+
+    fn save(&self, timestamp: u64) -> Vec<u8> {
+        let mut save = Vec::with_capacity(RealTimeClock::SAVE_SIZE);
+        for registers in &[self.live, self.latched] {
+            for register in RealTimeClock::SECONDS_REGISTER..=RealTimeClock::DAYS_HIGH_REGISTER {
+                save.extend_from_slice(&u32::from(registers.read(register)).to_le_bytes());
+            }
+        }
+        save.extend_from_slice(&timestamp.to_le_bytes());
+        save
+    }
+
+    // saves too short to hold a clock are ignored, the clock then starts at zero
+    fn load(&mut self, save: &[u8], timestamp: u64) {
+        let saved_timestamp = match save.len() {
+            length if length >= RealTimeClock::SAVE_SIZE => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(&save[40..48]);
+                u64::from_le_bytes(bytes)
+            }
+            length if length >= RealTimeClock::OLD_SAVE_SIZE => {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(&save[40..44]);
+                u64::from(u32::from_le_bytes(bytes))
+            }
+            _ => return,
+        };
+
+        // only the low byte of each word holds register bits
+        for (index, register) in (RealTimeClock::SECONDS_REGISTER..=RealTimeClock::DAYS_HIGH_REGISTER).enumerate() {
+            self.live.write(register, save[index * 4]);
+            self.latched.write(register, save[20 + index * 4]);
+        }
+        self.cycles = 0;
+        if !self.live.halted {
+            self.advance(timestamp.saturating_sub(saved_timestamp));
+        }
+    }
+
+    // ticks until out of range registers wrap, the rest is counted in one go
+    fn advance(&mut self, mut seconds: u64) {
+        while seconds > 0 && (self.live.seconds >= 60 || self.live.minutes >= 60 || self.live.hours >= 24) {
+            self.tick();
+            seconds -= 1;
+        }
+
+        let total = u64::from(self.live.days) * 86400
+            + u64::from(self.live.hours) * 3600
+            + u64::from(self.live.minutes) * 60
+            + u64::from(self.live.seconds)
+            + seconds;
+        let days = total / 86400;
+        self.live.days = (days & 0x1FF) as u16;
+        self.live.day_carry |= days > 0x1FF;
+        self.live.hours = (total / 3600 % 24) as u8;
+        self.live.minutes = (total / 60 % 60) as u8;
+        self.live.seconds = (total % 60) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{RAM_BANK_SIZE, ROM_BANK_SIZE};
+    use super::*;
+
+    fn read_rtc_register(mbc: &mut Mbc3, register: u8) -> u8 {
+        mbc.write_rom(0x4000, register);
+        mbc.read_ram(&[], 0xA000)
+    }
+
+    fn write_rtc_register(mbc: &mut Mbc3, register: u8, value: u8) {
+        mbc.write_rom(0x4000, register);
+        mbc.write_ram(&mut [], 0xA000, value);
+    }
+
+    fn latch(mbc: &mut Mbc3) {
+        mbc.write_rom(0x6000, 0x00);
+        mbc.write_rom(0x6000, 0x01);
+    }
+
+    #[test]
+    fn mbc3_banking_test() {
+        let mut rom = vec![0; 128 * ROM_BANK_SIZE];
+        for bank in 0..128 {
+            rom[bank * ROM_BANK_SIZE] = bank as u8;
+        }
+        let mut ram = vec![0; 4 * RAM_BANK_SIZE];
+        let mut mbc = Mbc3::new(false);
+
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+        mbc.write_rom(0x2000, 0x7F);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x7F);
+        // unlike MBC1, 0x20 is a valid bank
+        mbc.write_rom(0x2000, 0x20);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x20);
+
+        mbc.write_ram(&mut ram, 0xA000, 0x12);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0xFF);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x02);
+        mbc.write_ram(&mut ram, 0xA000, 0x12);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0x12);
+        assert_eq!(ram[2 * RAM_BANK_SIZE], 0x12);
+
+        // no RTC on this cartridge
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 0xFF);
+    }
+
+    #[test]
+    fn mbc3_rtc_latch_test() {
+        let mut mbc = Mbc3::new(true);
+        mbc.write_rom(0x0000, 0x0A);
+
+        mbc.step(RealTimeClock::CYCLES_PER_SECOND * 5);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 0);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 5);
+
+        // latched values do not change until the next latch
+        mbc.step(RealTimeClock::CYCLES_PER_SECOND);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 5);
+        // writing 0x01 again without 0x00 does not latch
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 5);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 6);
+
+        // disabled RTC reads as 0xFF
+        mbc.write_rom(0x0000, 0x00);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 0xFF);
+    }
+
+    #[test]
+    fn mbc3_rtc_counting_test() {
+        let mut mbc = Mbc3::new(true);
+        mbc.write_rom(0x0000, 0x0A);
+
+        write_rtc_register(&mut mbc, 0x08, 59);
+        write_rtc_register(&mut mbc, 0x09, 59);
+        write_rtc_register(&mut mbc, 0x0A, 23);
+        write_rtc_register(&mut mbc, 0x0B, 0xFF);
+        write_rtc_register(&mut mbc, 0x0C, 0x01);
+        mbc.step(RealTimeClock::CYCLES_PER_SECOND);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x09), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0A), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0B), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0C), 0b1000_0000);
+
+        // the carry flag stays set until cleared
+        for _ in 0..86400 {
+            mbc.step(RealTimeClock::CYCLES_PER_SECOND);
+        }
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0B), 1);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0C), 0b1000_0000);
+        write_rtc_register(&mut mbc, 0x0C, 0x00);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0C), 0b0000_0000);
+
+        // out of range values wrap without incrementing the next counter
+        write_rtc_register(&mut mbc, 0x08, 63);
+        write_rtc_register(&mut mbc, 0x09, 10);
+        mbc.step(RealTimeClock::CYCLES_PER_SECOND);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x09), 10);
+    }
+
+    #[test]
+    fn mbc3_rtc_halt_test() {
+        let mut mbc = Mbc3::new(true);
+        mbc.write_rom(0x0000, 0x0A);
+
+        write_rtc_register(&mut mbc, 0x0C, 0b0100_0000);
+        mbc.step(RealTimeClock::CYCLES_PER_SECOND * 10);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0C), 0b0100_0000);
+
+        // writing seconds resets the sub-second counter
+        write_rtc_register(&mut mbc, 0x0C, 0b0000_0000);
+        mbc.step(RealTimeClock::CYCLES_PER_SECOND - 1);
+        write_rtc_register(&mut mbc, 0x08, 30);
+        mbc.step(1);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 30);
+    }
+
+    #[test]
+    fn mbc3_rtc_save_test() {
+        let mut mbc = Mbc3::new(true);
+        mbc.write_rom(0x0000, 0x0A);
+        write_rtc_register(&mut mbc, 0x08, 50);
+        write_rtc_register(&mut mbc, 0x09, 59);
+        write_rtc_register(&mut mbc, 0x0A, 23);
+        write_rtc_register(&mut mbc, 0x0B, 0xFF);
+        write_rtc_register(&mut mbc, 0x0C, 0x00);
+        latch(&mut mbc);
+        write_rtc_register(&mut mbc, 0x0B, 0x10);
+
+        let save = mbc.save_rtc(1_000_000);
+        assert_eq!(save.len(), 48);
+        assert_eq!(save[..20], [50, 0, 0, 0, 59, 0, 0, 0, 23, 0, 0, 0, 0x10, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(save[32], 0xFF);
+        assert_eq!(save[40..], 1_000_000u64.to_le_bytes());
+
+        // two days and 15 seconds later the clock has moved on, the latched registers have not
+        let mut mbc = Mbc3::new(true);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.load_rtc(&save, 1_000_000 + 2 * 86400 + 15);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 50);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0B), 0xFF);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 5);
+        assert_eq!(read_rtc_register(&mut mbc, 0x09), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0A), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0B), 0x13);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0C), 0x00);
+
+        // the day counter overflows into the carry, a halted clock stays put
+        let mut save = save;
+        save[16] = 0x01;
+        mbc.load_rtc(&save, 1_000_000 + 0xF0 * 86400);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0B), 0x00);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0C), 0x80);
+        save[16] = 0x40;
+        mbc.load_rtc(&save, 1_000_000 + 86400);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 50);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0B), 0x10);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0C), 0x40);
+
+        // 44 byte saves with a 32-bit timestamp
+        save[16] = 0x00;
+        save.truncate(44);
+        mbc.load_rtc(&save, 1_000_000 + 10);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x09), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0B), 0x11);
+    }
+
+    #[test]
+    fn mbc3_rtc_set_time_test() {
+        let mut mbc = Mbc3::new(true);
+        mbc.write_rom(0x0000, 0x0A);
+
+        mbc.set_rtc_time(300 * 86400 + 13 * 3600 + 37 * 60 + 42);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 42);
+        assert_eq!(read_rtc_register(&mut mbc, 0x09), 37);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0A), 13);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0B), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0C), 0);
+
+        // 2026-10-18 08:15:30 UTC, about 20.7k days after the epoch
+        write_rtc_register(&mut mbc, 0x0C, 0x81);
+        mbc.set_rtc_time(1_792_311_330);
+        latch(&mut mbc);
+        assert_eq!(read_rtc_register(&mut mbc, 0x08), 30);
+        assert_eq!(read_rtc_register(&mut mbc, 0x09), 15);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0A), 8);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0B), 0);
+        assert_eq!(read_rtc_register(&mut mbc, 0x0C), 0);
+    }
+}
@@ -0,0 +1,129 @@
+use super::{ram_offset, rom_offset, store, MemoryBankController};
+
+pub struct Mbc5 {
+    ram_enabled: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    // rumble cartridges wire bit 3 of the RAM bank register to the motor
+    rumble_cartridge: bool,
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub fn new(rumble_cartridge: bool) -> Mbc5 {
+        Mbc5 {
+            ram_enabled: false,
+            rom_bank: 0x01,
+            ram_bank: 0x00,
+            rumble_cartridge,
+            rumble: false,
+        }
+    }
+}
+
+impl MemoryBankController for Mbc5 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => rom[rom_offset(rom, 0, address)],
+            _ => rom[rom_offset(rom, usize::from(self.rom_bank), address)],
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            // unlike MBC1 all 8 bits are compared
+            0x0000..=0x1FFF => self.ram_enabled = value == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | u16::from(value),
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | u16::from(value & 0x01) << 8,
+            0x4000..=0x5FFF => {
+                if self.rumble_cartridge {
+                    self.rumble = value & 0x08 != 0;
+                    self.ram_bank = value & 0x07;
+                } else {
+                    self.ram_bank = value & 0x0F;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled || ram.is_empty() {
+            return 0xFF;
+        }
+        ram[ram_offset(ram, usize::from(self.ram_bank), address)]
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) -> bool {
+        self.ram_enabled && !ram.is_empty() && store(&mut ram[ram_offset(ram, usize::from(self.ram_bank), address)], value)
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{RAM_BANK_SIZE, ROM_BANK_SIZE};
+    use super::*;
+
+    #[test]
+    fn mbc5_rom_banking_test() {
+        let mut rom = vec![0; 512 * ROM_BANK_SIZE];
+        for bank in 0..512 {
+            rom[bank * ROM_BANK_SIZE] = (bank & 0xFF) as u8;
+            rom[bank * ROM_BANK_SIZE + 1] = (bank >> 8) as u8;
+        }
+        let mut mbc = Mbc5::new(false);
+
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+        // bank 0 can be mapped to 0x4000 - 0x7FFF
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x00);
+
+        mbc.write_rom(0x2FFF, 0xAB);
+        mbc.write_rom(0x3000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0xAB);
+        assert_eq!(mbc.read_rom(&rom, 0x4001), 0x01);
+
+        // only bit 0 of the upper register is used
+        mbc.write_rom(0x3FFF, 0xFE);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0xAB);
+        assert_eq!(mbc.read_rom(&rom, 0x4001), 0x00);
+    }
+
+    #[test]
+    fn mbc5_ram_test() {
+        let mut ram = vec![0; 16 * RAM_BANK_SIZE];
+        let mut mbc = Mbc5::new(false);
+
+        mbc.write_rom(0x0000, 0x1A);
+        mbc.write_ram(&mut ram, 0xA000, 0x12);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0xFF);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x0F);
+        mbc.write_ram(&mut ram, 0xA000, 0x12);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0x12);
+        assert_eq!(ram[15 * RAM_BANK_SIZE], 0x12);
+        assert!(!mbc.rumble());
+    }
+
+    #[test]
+    fn mbc5_rumble_test() {
+        let mut ram = vec![0; 8 * RAM_BANK_SIZE];
+        let mut mbc = Mbc5::new(true);
+        mbc.write_rom(0x0000, 0x0A);
+
+        mbc.write_rom(0x4000, 0x0B);
+        assert!(mbc.rumble());
+        mbc.write_ram(&mut ram, 0xA000, 0x12);
+        assert_eq!(ram[3 * RAM_BANK_SIZE], 0x12);
+
+        mbc.write_rom(0x4000, 0x03);
+        assert!(!mbc.rumble());
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0x12);
+    }
+}
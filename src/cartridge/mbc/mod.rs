@@ -0,0 +1,57 @@
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod rom_only;
+
+pub use mbc1::Mbc1;
+pub use mbc2::Mbc2;
+pub use mbc3::Mbc3;
+pub use mbc5::Mbc5;
+pub use rom_only::RomOnly;
+use std::mem;
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+// memory bank controllers map the cartridge ROM and RAM into 0x0000 - 0x7FFF and 0xA000 - 0xBFFF,
+// writes to the ROM area are used to configure the controller
+pub trait MemoryBankController {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8;
+    fn write_rom(&mut self, address: u16, value: u8);
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8;
+    // returns whether a byte of RAM or a clock register changed, so saves are only rewritten when needed
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) -> bool;
+
+    // advances cartridge hardware running off the system clock, like the MBC3 real-time clock
+    fn step(&mut self, _cycles: u32) {}
+
+    // sets the real-time clock, if present, to the time of day of a Unix timestamp
+    fn set_rtc_time(&mut self, _timestamp: u64) {}
+
+    // the real-time clock state stored after the RAM in .sav files, empty without a clock
+    fn save_rtc(&self, _timestamp: u64) -> Vec<u8> {
+        Vec::new()
+    }
+
+    // restores a saved real-time clock and advances it by the time passed since the save
+    fn load_rtc(&mut self, _save: &[u8], _timestamp: u64) {}
+
+    // state of the rumble motor on cartridges having one
+    fn rumble(&self) -> bool {
+        false
+    }
+}
+
+pub fn rom_offset(rom: &[u8], bank: usize, address: u16) -> usize {
+    let bank_count = rom.len() / ROM_BANK_SIZE;
+    (bank % bank_count) * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1))
+}
+
+pub fn store(byte: &mut u8, value: u8) -> bool {
+    mem::replace(byte, value) != value
+}
+
+pub fn ram_offset(ram: &[u8], bank: usize, address: u16) -> usize {
+    (bank * RAM_BANK_SIZE + (address as usize & (RAM_BANK_SIZE - 1))) % ram.len()
+}
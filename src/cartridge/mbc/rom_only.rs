@@ -0,0 +1,22 @@
+use super::{store, MemoryBankController};
+
+pub struct RomOnly;
+
+impl MemoryBankController for RomOnly {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        rom.get(address as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        ram.get(address as usize - 0xA000).copied().unwrap_or(0xFF)
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) -> bool {
+        match ram.get_mut(address as usize - 0xA000) {
+            Some(byte) => store(byte, value),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,598 @@
+mod header;
+mod mbc;
+
+pub use header::{CartridgeType, CgbFlag, Destination, Header, Mbc};
+use mbc::{MemoryBankController, Mbc1, Mbc2, Mbc3, Mbc5, RomOnly, ROM_BANK_SIZE};
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    SaveFile(io::Error),
+    Truncated { expected: usize, actual: usize },
+    RomSizeMismatch { expected: usize, actual: usize },
+    InvalidRomSize(u8),
+    InvalidRamSize(u8),
+    UnsupportedCartridgeType(u8),
+    HeaderChecksumMismatch { expected: u8, computed: u8 },
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartridgeError::Io(error) => write!(f, "cannot read ROM file: {}", error),
+            CartridgeError::SaveFile(error) => write!(f, "cannot read save file: {}", error),
+            CartridgeError::Truncated { expected, actual } => {
+                write!(f, "ROM image is truncated: expected {} bytes, got {}", expected, actual)
+            }
+            CartridgeError::RomSizeMismatch { expected, actual } => {
+                write!(f, "ROM image size {} does not match size {} declared in the header", actual, expected)
+            }
+            CartridgeError::InvalidRomSize(code) => write!(f, "invalid ROM size code 0x{:02X}", code),
+            CartridgeError::InvalidRamSize(code) => write!(f, "invalid RAM size code 0x{:02X}", code),
+            CartridgeError::UnsupportedCartridgeType(code) => {
+                write!(f, "unsupported cartridge type 0x{:02X}", code)
+            }
+            CartridgeError::HeaderChecksumMismatch { expected, computed } => write!(
+                f,
+                "header checksum mismatch: expected 0x{:02X}, computed 0x{:02X}",
+                expected, computed
+            ),
+        }
+    }
+}
+
+impl Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CartridgeError::Io(error) | CartridgeError::SaveFile(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(error: io::Error) -> CartridgeError {
+        CartridgeError::Io(error)
+    }
+}
+
+// dirty save RAM is written out once per second of emulated time
+const SAVE_FLUSH_INTERVAL: u32 = 4_194_304;
+
+pub struct Cartridge {
+    header: Header,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mbc: Box<dyn MemoryBankController>,
+    global_checksum: u16,
+    save_path: Option<PathBuf>,
+    ram_dirty: bool,
+    cycles_since_flush: u32,
+    // the last failed automatic flush, kept until the host takes it
+    save_error: Option<io::Error>,
+}
+
+impl Cartridge {
+    // battery-backed RAM is loaded from, and later saved to, a .sav file next to the ROM, a real-time
+    // clock is kept after the RAM
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Cartridge, CartridgeError> {
+        let mut cartridge = Cartridge::from_bytes(fs::read(&path)?)?;
+        let cartridge_type = cartridge.header.cartridge_type;
+        if cartridge_type.battery && (!cartridge.ram.is_empty() || cartridge_type.timer) {
+            let save_path = path.as_ref().with_extension("sav");
+            match fs::read(&save_path) {
+                Ok(save) => {
+                    let length = save.len().min(cartridge.ram.len());
+                    cartridge.ram[..length].copy_from_slice(&save[..length]);
+                    cartridge.mbc.load_rtc(&save[length..], unix_time());
+                }
+                Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+                Err(error) => return Err(CartridgeError::SaveFile(error)),
+            }
+            cartridge.save_path = Some(save_path);
+        }
+        Ok(cartridge)
+    }
+
+    pub fn from_bytes(rom: Vec<u8>) -> Result<Cartridge, CartridgeError> {
+        let header = Header::parse(&rom)?;
+
+        if rom.len() < header.rom_size {
+            return Err(CartridgeError::Truncated {
+                expected: header.rom_size,
+                actual: rom.len(),
+            });
+        }
+        if rom.len() > header.rom_size {
+            return Err(CartridgeError::RomSizeMismatch {
+                expected: header.rom_size,
+                actual: rom.len(),
+            });
+        }
+
+        // the boot ROM never checks the global checksum and plenty of dumps and hacks get it wrong
+        let global_checksum = Header::compute_global_checksum(&rom);
+
+        let mbc: Box<dyn MemoryBankController> = match header.cartridge_type.mbc {
+            Mbc::RomOnly => Box::new(RomOnly),
+            Mbc::Mbc1 => Box::new(Mbc1::new(Cartridge::is_mbc1_multicart(&rom))),
+            Mbc::Mbc2 => Box::new(Mbc2::new()),
+            Mbc::Mbc3 => Box::new(Mbc3::new(header.cartridge_type.timer)),
+            Mbc::Mbc5 => Box::new(Mbc5::new(header.cartridge_type.rumble)),
+        };
+
+        let ram_size = match header.cartridge_type {
+            CartridgeType { mbc: Mbc::Mbc2, .. } => Mbc2::RAM_SIZE,
+            CartridgeType { ram: true, .. } => header.ram_size,
+            _ => 0,
+        };
+        Ok(Cartridge {
+            header,
+            rom,
+            ram: vec![0; ram_size],
+            mbc,
+            global_checksum,
+            save_path: None,
+            ram_dirty: false,
+            cycles_since_flush: 0,
+            save_error: None,
+        })
+    }
+
+    // MBC1M multicarts are 8 Mbit ROMs with a game, thus a Nintendo logo, at every 16th bank
+    fn is_mbc1_multicart(rom: &[u8]) -> bool {
+        const MULTICART_ROM_SIZE: usize = 0x100000;
+        const SECOND_GAME_OFFSET: usize = 0x10 * ROM_BANK_SIZE;
+
+        rom.len() == MULTICART_ROM_SIZE
+            && rom[SECOND_GAME_OFFSET + Header::LOGO..SECOND_GAME_OFFSET + Header::LOGO + Header::NINTENDO_LOGO.len()]
+                == Header::NINTENDO_LOGO[..]
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    // the checksum computed over the ROM image, for hosts that want to warn when it disagrees with the header
+    pub fn global_checksum(&self) -> u16 {
+        self.global_checksum
+    }
+
+    pub fn global_checksum_matches(&self) -> bool {
+        self.global_checksum == self.header.global_checksum
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x7FFF => self.mbc.read_rom(&self.rom, address),
+            0xA000..=0xBFFF => self.mbc.read_ram(&self.ram, address),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x7FFF => self.mbc.write_rom(address, value),
+            0xA000..=0xBFFF => self.ram_dirty |= self.mbc.write_ram(&mut self.ram, address, value),
+            _ => (),
+        }
+    }
+
+    pub fn step(&mut self, cycles: u32) {
+        self.mbc.step(cycles);
+
+        self.cycles_since_flush += cycles;
+        if self.cycles_since_flush >= SAVE_FLUSH_INTERVAL {
+            self.cycles_since_flush -= SAVE_FLUSH_INTERVAL;
+            // the RAM stays dirty on failure so the next interval retries
+            if let Err(error) = self.flush_save() {
+                self.save_error = Some(error);
+            }
+        }
+    }
+
+    pub fn take_save_error(&mut self) -> Option<io::Error> {
+        self.save_error.take()
+    }
+
+    // the save is written to a temporary file first so a crash mid-write keeps the previous save intact
+    pub fn flush_save(&mut self) -> io::Result<()> {
+        let save_path = match &self.save_path {
+            Some(save_path) if self.ram_dirty => save_path,
+            _ => return Ok(()),
+        };
+
+        let temporary_path = save_path.with_extension("sav.tmp");
+        let mut file = fs::File::create(&temporary_path)?;
+        file.write_all(&self.ram)?;
+        file.write_all(&self.mbc.save_rtc(unix_time()))?;
+        file.sync_all()?;
+        fs::rename(&temporary_path, save_path)?;
+
+        self.ram_dirty = false;
+        Ok(())
+    }
+
+    pub fn rumble(&self) -> bool {
+        self.mbc.rumble()
+    }
+
+    // by default the real-time clock starts at zero and advances with emulated cycles only, syncing
+    // sets it to the host's UTC time of day with the day counter at 0
+    pub fn sync_rtc_to_host_time(&mut self) {
+        self.mbc.set_rtc_time(unix_time());
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+// a last flush for hosts that drop the cartridge with unsaved RAM, there is nobody left to hand a
+// failure to so it is only printed, hosts that care call flush_save themselves before dropping
+impl Drop for Cartridge {
+    fn drop(&mut self) {
+        if !self.ram_dirty {
+            return;
+        }
+        if let Err(error) = self.flush_save() {
+            eprintln!("cannot write save file: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+pub fn test_rom(cartridge_type: u8, rom_size_code: u8, ram_size_code: u8) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000 << rom_size_code];
+    rom[0x0134..0x0138].copy_from_slice(b"TEST");
+    rom[0x0147] = cartridge_type;
+    rom[0x0148] = rom_size_code;
+    rom[0x0149] = ram_size_code;
+    update_checksums(&mut rom);
+    rom
+}
+
+#[cfg(test)]
+pub fn update_checksums(rom: &mut [u8]) {
+    rom[0x014D] = Header::compute_header_checksum(rom);
+    let global_checksum = Header::compute_global_checksum(rom);
+    rom[0x014E] = (global_checksum >> 8) as u8;
+    rom[0x014F] = (global_checksum & 0xFF) as u8;
+}
+
+#[cfg(test)]
+pub fn test_cartridge() -> Cartridge {
+    Cartridge::from_bytes(test_rom(0x00, 0x00, 0x00)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cartridge_header_test() {
+        let mut rom = test_rom(0x13, 0x02, 0x03);
+        rom[0x0134..0x0143].copy_from_slice(b"POKEMON_SLVAAXE");
+        rom[0x0143] = 0x80;
+        rom[0x0144..0x0146].copy_from_slice(b"01");
+        rom[0x0146] = 0x03;
+        rom[0x014A] = 0x01;
+        rom[0x014B] = 0x33;
+        rom[0x014C] = 0x02;
+        update_checksums(&mut rom);
+
+        let header = Header::parse(&rom).unwrap();
+        assert_eq!(header.title, "POKEMON_SLV");
+        assert_eq!(header.manufacturer_code, "AAXE");
+        assert_eq!(header.cgb_flag, CgbFlag::CgbSupported);
+        assert_eq!(header.new_licensee_code, "01");
+        assert!(header.sgb_flag);
+        assert_eq!(
+            header.cartridge_type,
+            CartridgeType {
+                mbc: Mbc::Mbc3,
+                ram: true,
+                battery: true,
+                timer: false,
+                rumble: false
+            }
+        );
+        assert_eq!(header.rom_size, 0x20000);
+        assert_eq!(header.ram_size, 0x8000);
+        assert_eq!(header.destination, Destination::NonJapanese);
+        assert_eq!(header.old_licensee_code, 0x33);
+        assert_eq!(header.version, 0x02);
+    }
+
+    #[test]
+    fn cartridge_dmg_title_test() {
+        let mut rom = test_rom(0x00, 0x00, 0x00);
+        rom[0x0134..0x0144].copy_from_slice(b"SUPER MARIOLAND ");
+        update_checksums(&mut rom);
+
+        let cartridge = Cartridge::from_bytes(rom).unwrap();
+        assert_eq!(cartridge.header().title, "SUPER MARIOLAND");
+        assert_eq!(cartridge.header().manufacturer_code, "");
+        assert_eq!(cartridge.header().cgb_flag, CgbFlag::DmgOnly);
+        assert_eq!(cartridge.header().destination, Destination::Japanese);
+    }
+
+    #[test]
+    fn cartridge_errors_test() {
+        let rom = test_rom(0x00, 0x00, 0x00);
+        assert!(matches!(
+            Cartridge::from_bytes(rom[..0x100].to_vec()),
+            Err(CartridgeError::Truncated { expected: 0x150, actual: 0x100 })
+        ));
+        assert!(matches!(
+            Cartridge::from_bytes(rom[..0x4000].to_vec()),
+            Err(CartridgeError::Truncated { expected: 0x8000, actual: 0x4000 })
+        ));
+
+        let mut oversized_rom = rom.clone();
+        oversized_rom.extend_from_slice(&[0; 0x4000]);
+        assert!(matches!(
+            Cartridge::from_bytes(oversized_rom),
+            Err(CartridgeError::RomSizeMismatch { expected: 0x8000, actual: 0xC000 })
+        ));
+
+        let mut corrupted_header = rom.clone();
+        corrupted_header[0x0134] = b'X';
+        assert!(matches!(
+            Cartridge::from_bytes(corrupted_header),
+            Err(CartridgeError::HeaderChecksumMismatch { .. })
+        ));
+
+        let cartridge = Cartridge::from_bytes(rom.clone()).unwrap();
+        assert!(cartridge.global_checksum_matches());
+
+        // a bad global checksum still loads
+        let mut corrupted_rom = rom.clone();
+        corrupted_rom[0x4000] = 0xAB;
+        let cartridge = Cartridge::from_bytes(corrupted_rom).unwrap();
+        assert!(!cartridge.global_checksum_matches());
+        assert_eq!(cartridge.global_checksum(), cartridge.header().global_checksum.wrapping_add(0xAB));
+        assert_eq!(cartridge.read_byte(0x4000), 0xAB);
+
+        assert!(matches!(
+            Cartridge::from_bytes(test_rom(0xFC, 0x00, 0x00)),
+            Err(CartridgeError::UnsupportedCartridgeType(0xFC))
+        ));
+
+        let mut invalid_rom_size = rom.clone();
+        invalid_rom_size[0x0148] = 0x09;
+        update_checksums(&mut invalid_rom_size);
+        assert!(matches!(
+            Cartridge::from_bytes(invalid_rom_size),
+            Err(CartridgeError::InvalidRomSize(0x09))
+        ));
+
+        let mut invalid_ram_size = rom;
+        invalid_ram_size[0x0149] = 0x06;
+        update_checksums(&mut invalid_ram_size);
+        assert!(matches!(
+            Cartridge::from_bytes(invalid_ram_size),
+            Err(CartridgeError::InvalidRamSize(0x06))
+        ));
+    }
+
+    #[test]
+    fn cartridge_rom_only_test() {
+        let mut rom = test_rom(0x00, 0x00, 0x00);
+        rom[0x0000] = 0x12;
+        rom[0x7FFF] = 0x34;
+        update_checksums(&mut rom);
+
+        let mut cartridge = Cartridge::from_bytes(rom).unwrap();
+        assert_eq!(cartridge.read_byte(0x0000), 0x12);
+        assert_eq!(cartridge.read_byte(0x7FFF), 0x34);
+
+        cartridge.write_byte(0x0000, 0xAB);
+        cartridge.write_byte(0xA000, 0xAB);
+        assert_eq!(cartridge.read_byte(0x0000), 0x12);
+        assert_eq!(cartridge.read_byte(0xA000), 0xFF);
+
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x08, 0x00, 0x02)).unwrap();
+        cartridge.write_byte(0xA000, 0xAB);
+        cartridge.write_byte(0xBFFF, 0xCD);
+        assert_eq!(cartridge.read_byte(0xA000), 0xAB);
+        assert_eq!(cartridge.read_byte(0xBFFF), 0xCD);
+    }
+
+    #[test]
+    fn cartridge_mbc1_test() {
+        let mut rom = test_rom(0x03, 0x02, 0x03);
+        rom[0x4000 * 5] = 0x05;
+        update_checksums(&mut rom);
+
+        let mut cartridge = Cartridge::from_bytes(rom).unwrap();
+        cartridge.write_byte(0x2000, 0x05);
+        assert_eq!(cartridge.read_byte(0x4000), 0x05);
+
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0x6000, 0x01);
+        cartridge.write_byte(0x4000, 0x03);
+        cartridge.write_byte(0xA000, 0xAB);
+        assert_eq!(cartridge.ram[3 * 0x2000], 0xAB);
+    }
+
+    #[test]
+    fn cartridge_mbc3_rtc_test() {
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x10, 0x02, 0x03)).unwrap();
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0x4000, 0x08);
+
+        cartridge.step(4_194_304 * 3);
+        cartridge.write_byte(0x6000, 0x00);
+        cartridge.write_byte(0x6000, 0x01);
+        assert_eq!(cartridge.read_byte(0xA000), 3);
+
+        cartridge.sync_rtc_to_host_time();
+        cartridge.write_byte(0x6000, 0x00);
+        cartridge.write_byte(0x6000, 0x01);
+        assert!(cartridge.read_byte(0xA000) < 60);
+        cartridge.write_byte(0x4000, 0x0C);
+        assert_eq!(cartridge.read_byte(0xA000) & 0b0100_0000, 0);
+    }
+
+    #[test]
+    fn cartridge_mbc2_test() {
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x06, 0x03, 0x00)).unwrap();
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0xA1FF, 0x12);
+        assert_eq!(cartridge.ram.len(), 0x200);
+        assert_eq!(cartridge.read_byte(0xA1FF), 0xF2);
+        assert_eq!(cartridge.read_byte(0xBFFF), 0xF2);
+    }
+
+    #[test]
+    fn cartridge_mbc5_rumble_test() {
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x1E, 0x02, 0x03)).unwrap();
+        assert!(!cartridge.rumble());
+        cartridge.write_byte(0x4000, 0x08);
+        assert!(cartridge.rumble());
+
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x1B, 0x02, 0x03)).unwrap();
+        cartridge.write_byte(0x4000, 0x08);
+        assert!(!cartridge.rumble());
+    }
+
+    #[test]
+    fn cartridge_save_file_test() {
+        let directory = std::env::temp_dir().join(format!("gbe-save-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let rom_path = directory.join("game.gb");
+        let save_path = directory.join("game.sav");
+        fs::write(&rom_path, test_rom(0x03, 0x00, 0x02)).unwrap();
+
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0xA000, 0x12);
+        cartridge.write_byte(0xBFFF, 0x34);
+        assert!(!save_path.exists());
+
+        // flushed once per second of emulated time
+        cartridge.step(SAVE_FLUSH_INTERVAL - 4);
+        assert!(!save_path.exists());
+        cartridge.step(4);
+        let save = fs::read(&save_path).unwrap();
+        assert_eq!(save.len(), 0x2000);
+        assert_eq!((save[0x0000], save[0x1FFF]), (0x12, 0x34));
+        assert!(!directory.join("game.sav.tmp").exists());
+
+        // writes that leave RAM as it was do not dirty it
+        fs::remove_file(&save_path).unwrap();
+        cartridge.write_byte(0xA000, 0x12);
+        cartridge.write_byte(0x0000, 0x00);
+        cartridge.write_byte(0xA000, 0x56);
+        cartridge.flush_save().unwrap();
+        assert!(!save_path.exists());
+
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0xA000, 0x56);
+        cartridge.flush_save().unwrap();
+        let cartridge = Cartridge::from_file(&rom_path).unwrap();
+        assert_eq!((cartridge.ram[0x0000], cartridge.ram[0x1FFF]), (0x56, 0x34));
+
+        // failed automatic flushes are kept for the host and retried
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.save_path = Some(directory.join("missing").join("game.sav"));
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0xA000, 0x9A);
+        cartridge.step(SAVE_FLUSH_INTERVAL);
+        assert!(cartridge.take_save_error().is_some());
+        assert!(cartridge.take_save_error().is_none());
+        assert!(cartridge.flush_save().is_err());
+        cartridge.save_path = None;
+
+        // dropping the cartridge flushes what is left
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0xA001, 0xBC);
+        drop(cartridge);
+        assert_eq!(fs::read(&save_path).unwrap()[0x0001], 0xBC);
+
+        // cartridges without a battery never touch the disk
+        fs::write(&rom_path, test_rom(0x02, 0x00, 0x02)).unwrap();
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        assert_eq!(cartridge.ram[0x0000], 0x00);
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0xA000, 0x78);
+        cartridge.flush_save().unwrap();
+        assert_eq!(fs::read(&save_path).unwrap()[0x0000], 0x56);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn cartridge_rtc_save_file_test() {
+        let directory = std::env::temp_dir().join(format!("gbe-rtc-save-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let rom_path = directory.join("game.gb");
+        let save_path = directory.join("game.sav");
+
+        // MBC3+TIMER+RAM+BATTERY, setting the clock dirties the save
+        fs::write(&rom_path, test_rom(0x10, 0x00, 0x02)).unwrap();
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0x4000, 0x0B);
+        cartridge.write_byte(0xA000, 0x2A);
+        cartridge.flush_save().unwrap();
+        let save = fs::read(&save_path).unwrap();
+        assert_eq!(save.len(), 0x2000 + 48);
+        assert_eq!(save[0x2000 + 12], 0x2A);
+
+        // a day passed while the emulator was not running
+        let mut save = save;
+        let timestamp = unix_time() - 86400;
+        save[0x2000 + 40..].copy_from_slice(&timestamp.to_le_bytes());
+        fs::write(&save_path, &save).unwrap();
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0x6000, 0x00);
+        cartridge.write_byte(0x6000, 0x01);
+        cartridge.write_byte(0x4000, 0x0B);
+        assert_eq!(cartridge.read_byte(0xA000), 0x2B);
+        drop(cartridge);
+
+        // MBC3+TIMER+BATTERY without RAM saves only the clock
+        fs::remove_file(&save_path).unwrap();
+        fs::write(&rom_path, test_rom(0x0F, 0x00, 0x00)).unwrap();
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0x4000, 0x0B);
+        cartridge.write_byte(0xA000, 0x07);
+        cartridge.flush_save().unwrap();
+        assert_eq!(fs::read(&save_path).unwrap().len(), 48);
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.write_byte(0x0000, 0x0A);
+        cartridge.write_byte(0x6000, 0x00);
+        cartridge.write_byte(0x6000, 0x01);
+        cartridge.write_byte(0x4000, 0x0B);
+        assert_eq!(cartridge.read_byte(0xA000), 0x07);
+        drop(cartridge);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn cartridge_mbc1_multicart_detection_test() {
+        let mut rom = test_rom(0x01, 0x05, 0x00);
+        assert!(!Cartridge::is_mbc1_multicart(&rom));
+
+        let logo_offset = 0x10 * 0x4000 + Header::LOGO;
+        rom[logo_offset..logo_offset + Header::NINTENDO_LOGO.len()].copy_from_slice(&Header::NINTENDO_LOGO);
+        assert!(Cartridge::is_mbc1_multicart(&rom));
+        assert!(!Cartridge::is_mbc1_multicart(&rom[..0x80000]));
+    }
+}
@@ -21,10 +21,15 @@ pub enum Instruction {
     Dec16(TargetRegister16),
     AddHL(u16),
     Swap(TargetRegister8),
+    Daa,
     Cpl,
     Ccf,
     Scf,
     Nop,
+    Halt,
+    Stop,
+    Di,
+    Ei,
     Rlc(TargetRegister8),
     Rl(TargetRegister8),
     Rrc(TargetRegister8),
@@ -45,6 +50,8 @@ pub enum Instruction {
     Rst(u8),
     Ret,
     Retcc(bool),
+    Reti,
+    Illegal(u8),
 }
 
 #[rustfmt::skip]
@@ -2,49 +2,148 @@ mod instruction;
 mod registers;
 
 use self::{instruction::*, registers::Registers};
-use super::Mmu;
+use super::mmu::{Interrupt, Mmu};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuState {
+    Running,
+    Halted,
+    Stopped,
+    // executing one of the undefined opcodes hangs the CPU until reset
+    Locked(u8),
+}
 
 pub struct Cpu {
     registers: Registers,
-    cycles: u8,
+    cycles: u32,
+    synced_cycles: u32,
+    ime: bool,
+    ime_enable_delay: u8,
+    state: CpuState,
+    halt_bug: bool,
 }
 
 impl Cpu {
+    const JOYPAD_ADDRESS: u16 = 0xFF00;
+    const DIVIDER_ADDRESS: u16 = 0xFF04;
+
     pub fn new() -> Cpu {
         Cpu {
             registers: Registers::new(),
             cycles: 0,
+            synced_cycles: 0,
+            ime: false,
+            ime_enable_delay: 0,
+            state: CpuState::Running,
+            halt_bug: false,
         }
     }
 
-    pub fn step(&mut self, mmu: &mut Mmu) {
-        let opcode = self.next_byte(mmu);
+    pub fn skip_boot(&mut self) {
+        self.registers = Registers::post_boot();
+    }
+
+    pub fn state(&self) -> CpuState {
+        self.state
+    }
 
-        let instruction;
-        if opcode == 0xCB {
-            print!("0x{:X}", opcode);
-            let prefixed_opcode = self.next_byte(mmu);
-            print!("{:X}:\t", prefixed_opcode);
-            instruction = self.decode_prefixed_opcode(prefixed_opcode);
-        } else {
-            print!("0x{:X}:\t", opcode);
-            instruction = self.decode_opcode(mmu, opcode);
+    // the rest of the system is stepped along with the CPU, so it sees every access at the right cycle
+    pub fn step(&mut self, mmu: &mut Mmu) -> u32 {
+        self.cycles = 0;
+        self.synced_cycles = 0;
+        self.execute(mmu);
+        self.sync(mmu);
+        self.cycles
+    }
+
+    fn execute(&mut self, mmu: &mut Mmu) {
+        match self.state {
+            CpuState::Running => (),
+            CpuState::Halted => {
+                if mmu.pending_interrupts() == 0 {
+                    self.delay(4);
+                    return;
+                }
+                self.state = CpuState::Running;
+            }
+            CpuState::Stopped => {
+                // any selected button line going low wakes the CPU up
+                if mmu.read_byte(Cpu::JOYPAD_ADDRESS) & 0x0F == 0x0F {
+                    self.delay(4);
+                    return;
+                }
+                self.state = CpuState::Running;
+            }
+            CpuState::Locked(_) => {
+                self.delay(4);
+                return;
+            }
+        }
+
+        if self.handle_interrupts(mmu) {
+            return;
         }
-        println!("{:?}", instruction);
+
+        let opcode = if self.halt_bug {
+            // the byte following HALT is read twice, as PC fails to increment
+            self.halt_bug = false;
+            self.read_byte(mmu, self.registers.get_pc())
+        } else {
+            self.next_byte(mmu)
+        };
+
+        let instruction = self.decode_opcode(mmu, opcode);
         self.execute_instruction(mmu, instruction);
+        self.update_ime();
     }
 
-    fn delay(&mut self, cycles: u8) {
+    fn delay(&mut self, cycles: u32) {
         self.cycles += cycles;
     }
 
-    fn read_byte(&mut self, mmu: &Mmu, address: u16) -> u8 {
+    fn sync(&mut self, mmu: &mut Mmu) {
+        mmu.step(self.cycles - self.synced_cycles);
+        self.synced_cycles = self.cycles;
+    }
+
+    fn handle_interrupts(&mut self, mmu: &mut Mmu) -> bool {
+        if !self.ime {
+            return false;
+        }
+
+        match Interrupt::highest_priority(mmu.pending_interrupts()) {
+            Some(interrupt) => {
+                self.ime = false;
+                mmu.acknowledge_interrupt(interrupt);
+                // two wait states and setting PC, pushing PC takes the remaining 8 cycles
+                self.delay(12);
+                self.push(mmu, self.registers.get_pc());
+                self.jump(interrupt.vector());
+                true
+            }
+            None => false,
+        }
+    }
+
+    // EI takes effect only after the instruction following it has been executed
+    fn update_ime(&mut self) {
+        if self.ime_enable_delay > 0 {
+            self.ime_enable_delay -= 1;
+            if self.ime_enable_delay == 0 {
+                self.ime = true;
+            }
+        }
+    }
+
+    fn read_byte(&mut self, mmu: &mut Mmu, address: u16) -> u8 {
         self.delay(4);
+        self.sync(mmu);
         mmu.read_byte(address)
     }
 
     fn write_byte(&mut self, mmu: &mut Mmu, address: u16, byte: u8) {
         self.delay(4);
+        self.sync(mmu);
         mmu.write_byte(address, byte);
     }
 
@@ -142,7 +241,7 @@ impl Cpu {
             0x12 => Instruction::LoadToMemory(self.registers.get_de(), LoadRegister::A),
             0x77 => Instruction::LoadToMemory(self.registers.get_hl(), LoadRegister::A),
             0xEA => Instruction::LoadToMemory(self.next_word(mmu), LoadRegister::A),
-            0xF2 => Instruction::Load(LoadRegister::A, self.read_byte(mmu, 0xFF00) + self.registers.get_c()),
+            0xF2 => Instruction::Load(LoadRegister::A, self.read_byte(mmu, 0xFF00 + u16::from(self.registers.get_c()))),
             0xE2 => Instruction::LoadToMemory(0xFF00 + u16::from(self.registers.get_c()), LoadRegister::A),
             0x3A => {
                 let address = self.registers.decrement_hl();
@@ -216,6 +315,7 @@ impl Cpu {
             0x9C => Instruction::Sbc(self.registers.get_h()),
             0x9D => Instruction::Sbc(self.registers.get_l()),
             0x9E => Instruction::Sbc(self.read_byte(mmu, self.registers.get_hl())),
+            0xDE => Instruction::Sbc(self.next_byte(mmu)),
             0xA7 => Instruction::And(self.registers.get_a()),
             0xA0 => Instruction::And(self.registers.get_b()),
             0xA1 => Instruction::And(self.registers.get_c()),
@@ -285,15 +385,19 @@ impl Cpu {
                 let byte = self.next_byte(mmu);
                 Instruction::Load16(TargetRegister16::SP, self.add_signed_byte_to_word(byte as i8, self.registers.get_sp()))
             }
-            0x27 => panic!("DAA instruction not implemented"),
+            0x27 => Instruction::Daa,
             0x2F => Instruction::Cpl,
             0x3F => Instruction::Ccf,
             0x37 => Instruction::Scf,
             0x00 => Instruction::Nop,
-            0x76 => panic!("HALT instruction not implemented"),
-            0x10 => panic!("STOP instruction not implemented"),
-            0xF3 => panic!("DI instruction not implemented"),
-            0xFB => panic!("EI instruction not implemented"),
+            0x76 => Instruction::Halt,
+            0x10 => {
+                // STOP is followed by a padding byte which is skipped
+                self.registers.get_and_increment_pc();
+                Instruction::Stop
+            }
+            0xF3 => Instruction::Di,
+            0xFB => Instruction::Ei,
             0x07 => Instruction::Rlc(TargetRegister8::A),
             0x17 => Instruction::Rl(TargetRegister8::A),
             0x0F => Instruction::Rrc(TargetRegister8::A),
@@ -327,8 +431,12 @@ impl Cpu {
             0xC8 => Instruction::Retcc(self.registers.get_z_flag()),
             0xD0 => Instruction::Retcc(!self.registers.get_c_flag()),
             0xD8 => Instruction::Retcc(self.registers.get_c_flag()),
-            0xD9 => panic!("RETI instruction not implemented"),
-            _ => panic!("unknown opcode {}", opcode)
+            0xD9 => Instruction::Reti,
+            0xCB => {
+                let prefixed_opcode = self.next_byte(mmu);
+                self.decode_prefixed_opcode(prefixed_opcode)
+            }
+            0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => Instruction::Illegal(opcode),
         }
     }
 
@@ -496,8 +604,6 @@ impl Cpu {
                 let bit = (opcode - 0x86) / 0x8;
                 Instruction::Res(TargetRegister8::HL, bit)
             }
-
-            _ => panic!("unknown opcode {}", opcode)
         }
     }
 
@@ -662,6 +768,7 @@ impl Cpu {
                 self.delay(4);
                 self.add16(*register_value)
             }
+            Instruction::Daa => self.decimal_adjust_a(),
             Instruction::Cpl => self.complement_a(),
             Instruction::Ccf => self.complement_carry_flag(),
             Instruction::Scf => {
@@ -670,6 +777,26 @@ impl Cpu {
                 self.registers.set_c_flag(true);
             }
             Instruction::Nop => (),
+            Instruction::Halt => {
+                if !self.ime && mmu.pending_interrupts() != 0 {
+                    self.halt_bug = true;
+                } else {
+                    self.state = CpuState::Halted;
+                }
+            }
+            Instruction::Stop => {
+                mmu.write_byte(Cpu::DIVIDER_ADDRESS, 0x0);
+                self.state = CpuState::Stopped;
+            }
+            Instruction::Di => {
+                self.ime = false;
+                self.ime_enable_delay = 0;
+            }
+            Instruction::Ei => {
+                if !self.ime {
+                    self.ime_enable_delay = 2;
+                }
+            }
             Instruction::Bit(target, bit) => {
                 match target {
                     TargetRegister8::A => self.bit(self.registers.get_a(), *bit),
@@ -744,6 +871,11 @@ impl Cpu {
                     self.ret(mmu);
                 }
             }
+            Instruction::Reti => {
+                self.ret(mmu);
+                self.ime = true;
+            }
+            Instruction::Illegal(opcode) => self.state = CpuState::Locked(*opcode),
         }
     }
 
@@ -782,16 +914,16 @@ impl Cpu {
         self.registers.set_z_flag(false);
         self.registers.set_n_flag(false);
 
-        let abs_value = byte.abs() as u16;
+        let abs_value = u16::from(byte.unsigned_abs());
         if byte >= 0 {
             self.registers.set_h_flag((word & 0xF) + (abs_value & 0xF) > 0xF);
             self.registers.set_c_flag((word & 0xFF) + abs_value > 0xFF);
-            return word + abs_value;
+            word + abs_value
         } else {
             let (result, overflowed) = word.overflowing_sub(abs_value);
             self.registers.set_h_flag(abs_value & 0xF > word & 0xF);
             self.registers.set_c_flag(overflowed);
-            return result;
+            result
         }
     }
 
@@ -882,8 +1014,33 @@ impl Cpu {
         result
     }
 
-    fn complement_a(&mut self) {
+    fn decimal_adjust_a(&mut self) {
         let mut value = self.registers.get_a();
+        let mut carry = self.registers.get_c_flag();
+        if self.registers.get_n_flag() {
+            if carry {
+                value = value.wrapping_sub(0x60);
+            }
+            if self.registers.get_h_flag() {
+                value = value.wrapping_sub(0x06);
+            }
+        } else {
+            if carry || value > 0x99 {
+                value = value.wrapping_add(0x60);
+                carry = true;
+            }
+            if self.registers.get_h_flag() || value & 0x0F > 0x09 {
+                value = value.wrapping_add(0x06);
+            }
+        }
+        self.registers.set_a(value);
+        self.registers.set_z_flag(value == 0);
+        self.registers.set_h_flag(false);
+        self.registers.set_c_flag(carry);
+    }
+
+    fn complement_a(&mut self) {
+        let value = self.registers.get_a();
         self.registers.set_a(!value);
         self.registers.set_n_flag(true);
         self.registers.set_h_flag(true);
@@ -994,7 +1151,7 @@ impl Cpu {
     fn restart(&mut self, mmu: &mut Mmu, offset: u8) {
         self.delay(20);
         self.push(mmu, self.registers.get_pc());
-        self.jump(0x0000 + u16::from(offset));
+        self.jump(u16::from(offset));
     }
 
     fn ret(&mut self, mmu: &mut Mmu) {
@@ -1003,14 +1160,32 @@ impl Cpu {
     }
 }
 
+impl Default for Cpu {
+    fn default() -> Cpu {
+        Cpu::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cartridge::test_cartridge;
+    use crate::joypad::Button;
+    use crate::mmu::BootRom;
+
+    // ROM is read-only, so test programs are placed in WRAM
+    const PROGRAM_ADDRESS: u16 = 0xC000;
+
+    fn test_cpu() -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.registers.set_pc(PROGRAM_ADDRESS);
+        cpu
+    }
 
     #[test]
     fn cpu_load8_immediate_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         mmu.write_byte(cpu.registers.get_pc(), 0xE0);
         let load_b = cpu.decode_opcode(&mut mmu, 0x06);
         cpu.execute_instruction(&mut mmu, load_b);
@@ -1042,7 +1217,8 @@ mod tests {
         assert_eq!(cpu.registers.get_h(), 0xE4);
         assert_eq!(cpu.registers.get_l(), 0xE5);
 
-        mmu.write_byte(0xFF00 + u16::from(mmu.read_byte(cpu.registers.get_pc())), 0xE6);
+        mmu.write_byte(cpu.registers.get_pc(), 0x80);
+        mmu.write_byte(0xFF80, 0xE6);
         let load_a = cpu.decode_opcode(&mut mmu, 0xF0);
         cpu.execute_instruction(&mut mmu, load_a);
         assert_eq!(cpu.registers.get_a(), 0xE6);
@@ -1050,9 +1226,9 @@ mod tests {
 
     #[test]
     fn cpu_load8_registers_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
-        const ADDRESS: u16 = 0xABCD;
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        const ADDRESS: u16 = 0xCBCD;
         cpu.registers.set_hl(ADDRESS);
         cpu.registers.set_bc(ADDRESS);
         cpu.registers.set_de(ADDRESS);
@@ -1090,7 +1266,7 @@ mod tests {
         // Load(A, nn)
         mmu.write_byte(ADDRESS, 0xFD);
         mmu.write_byte(cpu.registers.get_pc(), 0xCD);
-        mmu.write_byte(cpu.registers.get_pc() + 1, 0xAB);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xCB);
         let load_bytes_to_a = cpu.decode_opcode(&mut mmu, 0xFA);
         cpu.execute_instruction(&mut mmu, load_bytes_to_a);
         assert_eq!(cpu.registers.get_a(), 0xFD);
@@ -1166,18 +1342,18 @@ mod tests {
         assert_eq!(mmu.read_byte(ADDRESS), 0xE8);
 
         // Load(A, (C))
-        mmu.write_byte(0xFF00, 0x1);
-        cpu.registers.set_c(0x2);
+        mmu.write_byte(0xFF82, 0x1);
+        cpu.registers.set_c(0x82);
         let load_to_a_0xff00_plus_c = cpu.decode_opcode(&mut mmu, 0xF2);
         cpu.execute_instruction(&mut mmu, load_to_a_0xff00_plus_c);
-        assert_eq!(cpu.registers.get_a(), 0x1 + 0x2);
+        assert_eq!(cpu.registers.get_a(), 0x1);
     }
 
     #[test]
     fn cpu_load8_to_memory_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
-        const ADDRESS: u16 = 0xABCD;
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        const ADDRESS: u16 = 0xCBCD;
         cpu.registers.set_bc(ADDRESS);
         cpu.registers.set_de(ADDRESS);
         cpu.registers.set_hl(ADDRESS);
@@ -1210,7 +1386,7 @@ mod tests {
         // Load(nn, A)
         cpu.registers.set_a(0xE8);
         mmu.write_byte(cpu.registers.get_pc(), 0xCD);
-        mmu.write_byte(cpu.registers.get_pc() + 1, 0xAB);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xCB);
         let load_to_memory_nn_from_a = cpu.decode_opcode(&mut mmu, 0xEA);
         cpu.execute_instruction(&mut mmu, load_to_memory_nn_from_a);
         assert_eq!(mmu.read_byte(ADDRESS), 0xE8);
@@ -1234,8 +1410,8 @@ mod tests {
 
     #[test]
     fn cpu_load16_immediate_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         // Load(BC, nn)
         mmu.write_byte(cpu.registers.get_pc(), 0xCD);
@@ -1272,9 +1448,11 @@ mod tests {
 
     #[test]
     fn cpu_load_stack_pointer_to_memory_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_sp(0xABCD);
+        mmu.write_byte(cpu.registers.get_pc(), 0x00);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xC0);
 
         let load_sp_to_memory = cpu.decode_opcode(&mut mmu, 0x08);
         cpu.execute_instruction(&mut mmu, load_sp_to_memory);
@@ -1286,8 +1464,8 @@ mod tests {
     #[test]
     fn cpu_stack_push_pop_test() {
         const INITIAL_SP: u16 = 0xFFFE;
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_sp(INITIAL_SP);
         cpu.registers.set_af(0x8890);
         cpu.registers.set_bc(0xAABB);
@@ -1334,8 +1512,8 @@ mod tests {
     #[test]
     fn cpu_add8_test() {
         // add without carry flag
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_a(0x5);
         cpu.registers.set_f(0b1101_0000);
         let add_a = cpu.decode_opcode(&mut mmu, 0x87);
@@ -1349,7 +1527,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0x16);
         assert_eq!(cpu.registers.get_f(), 0b0010_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0xFF);
         cpu.registers.set_hl(ADDRESS);
         let add_from_memory_hl = cpu.decode_opcode(&mut mmu, 0x86);
@@ -1367,8 +1545,8 @@ mod tests {
 
     #[test]
     fn cpu_add_signed_byte_to_word_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_f(0b1100_0000);
 
         // positive byte
@@ -1412,8 +1590,8 @@ mod tests {
     #[test]
     fn cpu_sub_test() {
         // sub without carry flag
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_a(0xFA);
         cpu.registers.set_b(0x15);
         cpu.registers.set_f(0b1000_0000);
@@ -1427,7 +1605,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0x0);
         assert_eq!(cpu.registers.get_f(), 0b1100_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0xF0);
         cpu.registers.set_hl(ADDRESS);
         let sub_from_memory_hl = cpu.decode_opcode(&mut mmu, 0x96);
@@ -1452,8 +1630,8 @@ mod tests {
 
     #[test]
     fn cpu_and_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_a(0xAA);
         let and_a = cpu.decode_opcode(&mut mmu, 0xA7);
         cpu.execute_instruction(&mut mmu, and_a);
@@ -1466,7 +1644,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0x0);
         assert_eq!(cpu.registers.get_f(), 0b1000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         cpu.registers.set_a(0xFF);
         mmu.write_byte(ADDRESS, 0xDE);
         cpu.registers.set_hl(ADDRESS);
@@ -1484,8 +1662,8 @@ mod tests {
 
     #[test]
     fn cpu_or_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_a(0x0);
         let or_a = cpu.decode_opcode(&mut mmu, 0xB7);
         cpu.execute_instruction(&mut mmu, or_a);
@@ -1498,7 +1676,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0x11);
         assert_eq!(cpu.registers.get_f(), 0b0000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0xAB);
         cpu.registers.set_hl(ADDRESS);
         let or_from_memory_hl = cpu.decode_opcode(&mut mmu, 0xB6);
@@ -1515,8 +1693,8 @@ mod tests {
 
     #[test]
     fn cpu_xor_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_a(0xDE);
         let xor_a = cpu.decode_opcode(&mut mmu, 0xAF);
         cpu.execute_instruction(&mut mmu, xor_a);
@@ -1529,7 +1707,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0xDF);
         assert_eq!(cpu.registers.get_f(), 0b0000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0xDF);
         cpu.registers.set_hl(ADDRESS);
         let xor_from_memory_hl = cpu.decode_opcode(&mut mmu, 0xAE);
@@ -1546,8 +1724,8 @@ mod tests {
 
     #[test]
     fn cpu_compare_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         // compare with itself
         cpu.registers.set_a(0xDE);
@@ -1574,7 +1752,7 @@ mod tests {
         cpu.execute_instruction(&mut mmu, compare_d);
         assert_eq!(cpu.registers.get_f(), 0b0110_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         const VALUE: u8 = 0x10;
         mmu.write_byte(ADDRESS, VALUE);
         cpu.registers.set_hl(ADDRESS);
@@ -1591,8 +1769,8 @@ mod tests {
 
     #[test]
     fn cpu_increment8_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_b(0xF);
         cpu.registers.set_f(0b1101_0000);
         let inc_b = cpu.decode_opcode(&mut mmu, 0x04);
@@ -1601,13 +1779,13 @@ mod tests {
         assert_eq!(cpu.registers.get_f(), 0b0011_0000);
 
         // check that zero flag is set
-        cpu.registers.set_c(u8::max_value());
+        cpu.registers.set_c(u8::MAX);
         let inc_c = cpu.decode_opcode(&mut mmu, 0x0C);
         cpu.execute_instruction(&mut mmu, inc_c);
         assert_eq!(cpu.registers.get_c(), 0x0);
         assert_eq!(cpu.registers.get_f(), 0b1011_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         const VALUE: u8 = 0x1F;
         mmu.write_byte(ADDRESS, VALUE);
         cpu.registers.set_hl(ADDRESS);
@@ -1618,8 +1796,8 @@ mod tests {
 
     #[test]
     fn cpu_decrement8_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_a(0xF);
         cpu.registers.set_f(0b1011_0000);
         cpu.execute_instruction(&mut mmu, Instruction::Dec8(TargetRegister8::A));
@@ -1640,7 +1818,7 @@ mod tests {
         assert_eq!(cpu.registers.get_c(), 0b1111);
         assert_eq!(cpu.registers.get_f(), 0b0111_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         const VALUE: u8 = 0x1F;
         mmu.write_byte(ADDRESS, VALUE);
         cpu.registers.set_hl(ADDRESS);
@@ -1652,8 +1830,8 @@ mod tests {
     #[test]
     fn cpu_increment16_decrement16_test() {
         const INITIAL_REGISTER_VALUE: u16 = 0xDEAD;
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_bc(INITIAL_REGISTER_VALUE);
         cpu.registers.set_de(INITIAL_REGISTER_VALUE);
         cpu.registers.set_hl(INITIAL_REGISTER_VALUE);
@@ -1690,8 +1868,8 @@ mod tests {
 
     #[test]
     fn cpu_add16_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         // check that substract flag is reset and half carry flag is set
         cpu.registers.set_hl(0xFFF);
@@ -1713,9 +1891,9 @@ mod tests {
 
     #[test]
     fn cpu_swap_test() {
-        const ADDRESS: u16 = 0xABCD;
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        const ADDRESS: u16 = 0xCBCD;
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_a(0b0);
         cpu.registers.set_b(0b1111_0000);
@@ -1747,8 +1925,8 @@ mod tests {
 
     #[test]
     fn cpu_complement_a_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
         cpu.registers.set_a(0b1001_0110);
         cpu.registers.set_f(0b1001_0000);
 
@@ -1761,8 +1939,8 @@ mod tests {
 
     #[test]
     fn cpu_complement_carry_flag_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_f(0b1000_0000);
         let complement_carry_flag = cpu.decode_opcode(&mut mmu, 0x3F);
@@ -1777,8 +1955,8 @@ mod tests {
 
     #[test]
     fn cpu_set_carry_flag_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_f(0b1110_0000);
         let set_carry_flag = cpu.decode_opcode(&mut mmu, 0x37);
@@ -1793,8 +1971,8 @@ mod tests {
 
     #[test]
     fn cpu_rlc_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_a(0b1100_0011);
         cpu.registers.set_f(0b1110_0000);
@@ -1817,7 +1995,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0b0000_0000);
         assert_eq!(cpu.registers.get_f(), 0b1000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0b0011_1100);
         cpu.registers.set_hl(ADDRESS);
         cpu.registers.set_f(0b0001_0000);
@@ -1829,8 +2007,8 @@ mod tests {
 
     #[test]
     fn cpu_rl_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_a(0b1100_0011);
         cpu.registers.set_f(0b1110_0000);
@@ -1853,7 +2031,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0b0000_0000);
         assert_eq!(cpu.registers.get_f(), 0b1000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0b0011_1100);
         cpu.registers.set_hl(ADDRESS);
         cpu.registers.set_f(0b0001_0000);
@@ -1865,8 +2043,8 @@ mod tests {
 
     #[test]
     fn cpu_rrc_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_a(0b1100_0011);
         cpu.registers.set_f(0b1110_0000);
@@ -1889,7 +2067,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0b0000_0000);
         assert_eq!(cpu.registers.get_f(), 0b1000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0b0011_1100);
         cpu.registers.set_hl(ADDRESS);
         cpu.registers.set_f(0b0001_0000);
@@ -1901,8 +2079,8 @@ mod tests {
 
     #[test]
     fn cpu_rr_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_a(0b1100_0011);
         cpu.registers.set_f(0b1110_0000);
@@ -1925,7 +2103,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0b0000_0000);
         assert_eq!(cpu.registers.get_f(), 0b1000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0b0011_1100);
         cpu.registers.set_hl(ADDRESS);
         cpu.registers.set_f(0b0001_0000);
@@ -1937,8 +2115,8 @@ mod tests {
 
     #[test]
     fn cpu_sla_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_a(0b1000_0011);
         cpu.registers.set_f(0b1110_0000);
@@ -1952,7 +2130,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0b0000_1100);
         assert_eq!(cpu.registers.get_f(), 0b0000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0b1000_0000);
         cpu.registers.set_hl(ADDRESS);
         let sla_hl = cpu.decode_prefixed_opcode(0x26);
@@ -1963,8 +2141,8 @@ mod tests {
 
     #[test]
     fn cpu_sra_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_a(0b1001_1001);
         cpu.registers.set_f(0b1110_0000);
@@ -1978,7 +2156,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0b1110_0110);
         assert_eq!(cpu.registers.get_f(), 0b0000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0b0000_0001);
         cpu.registers.set_hl(ADDRESS);
         let sra_hl = cpu.decode_prefixed_opcode(0x2E);
@@ -1989,8 +2167,8 @@ mod tests {
 
     #[test]
     fn cpu_srl_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_a(0b1001_1001);
         cpu.registers.set_f(0b1110_0000);
@@ -2004,7 +2182,7 @@ mod tests {
         assert_eq!(cpu.registers.get_a(), 0b0010_0110);
         assert_eq!(cpu.registers.get_f(), 0b0000_0000);
 
-        const ADDRESS: u16 = 0xABCD;
+        const ADDRESS: u16 = 0xCBCD;
         mmu.write_byte(ADDRESS, 0b0000_0001);
         cpu.registers.set_hl(ADDRESS);
         let srl_hl = cpu.decode_prefixed_opcode(0x3E);
@@ -2015,15 +2193,15 @@ mod tests {
 
     #[test]
     fn cpu_bit_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         cpu.registers.set_a(0b0000_0000);
         cpu.registers.set_f(0b1111_0000);
         let bit = cpu.decode_prefixed_opcode(0x47);
         cpu.execute_instruction(&mut mmu, bit);
         assert_eq!(cpu.registers.get_f(), 0b1011_0000);
-        assert_eq!(cpu.registers.get_z_flag(), true);
+        assert!(cpu.registers.get_z_flag());
 
         let mut test_register = |register, opcodes: Vec<u8>| {
             for (i, current_opcode) in opcodes.iter().enumerate() {
@@ -2055,12 +2233,12 @@ mod tests {
                 }
                 let bit = cpu.decode_prefixed_opcode(*current_opcode);
                 cpu.execute_instruction(&mut mmu, bit);
-                assert_eq!(cpu.registers.get_z_flag(), false);
+                assert!(!cpu.registers.get_z_flag());
 
                 for not_current_opcode in opcodes.iter().filter(|&o| o != current_opcode) {
                     let bit = cpu.decode_prefixed_opcode(*not_current_opcode);
                     cpu.execute_instruction(&mut mmu, bit);
-                    assert_eq!(cpu.registers.get_z_flag(), true);
+                    assert!(cpu.registers.get_z_flag());
                 }
             }
         };
@@ -2092,8 +2270,8 @@ mod tests {
 
     #[test]
     fn cpu_set_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         let mut test_register = |cpu: &mut Cpu, register, opcodes: Vec<u8>| {
             let mut previous_value = 0;
@@ -2136,17 +2314,17 @@ mod tests {
         let register_l_opcodes = vec![0xC5, 0xCD, 0xD5, 0xDD, 0xE5, 0xED, 0xF5, 0xFD];
         test_register(&mut cpu, TargetRegister8::L, register_l_opcodes);
 
-        cpu.registers.set_hl(0xABCD);
+        cpu.registers.set_hl(0xCBCD);
         let register_hl_opcodes = vec![0xC6, 0xCE, 0xD6, 0xDE, 0xE6, 0xEE, 0xF6, 0xFE];
         test_register(&mut cpu, TargetRegister8::HL, register_hl_opcodes);
     }
 
     #[test]
     fn cpu_reset_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
-        let mut test_register = |cpu: &mut Cpu, mmu: &mut Mmu, register, opcodes: Vec<u8>| {
+        let test_register = |cpu: &mut Cpu, mmu: &mut Mmu, register, opcodes: Vec<u8>| {
             let mut previous_value = 0b1111_1111;
             for (i, opcode) in opcodes.iter().enumerate() {
                 let set = cpu.decode_prefixed_opcode(*opcode);
@@ -2195,22 +2373,22 @@ mod tests {
         test_register(&mut cpu, &mut mmu,  TargetRegister8::L, register_l_opcodes);
 
         let register_hl_opcodes = vec![0x86, 0x8E, 0x96, 0x9E, 0xA6, 0xAE, 0xB6, 0xBE];
-        cpu.registers.set_hl(0xABCD);
+        cpu.registers.set_hl(0xCBCD);
         mmu.write_byte(cpu.registers.get_hl(), 0b1111_1111);
         test_register(&mut cpu, &mut mmu,  TargetRegister8::HL, register_hl_opcodes);
     }
 
     #[test]
     fn cpu_jp_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         // jump
         mmu.write_byte(cpu.registers.get_pc(), 0xCD);
-        mmu.write_byte(cpu.registers.get_pc() + 1, 0xAB);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xCB);
         let jp = cpu.decode_opcode(&mut mmu, 0xC3);
         cpu.execute_instruction(&mut mmu, jp);
-        assert_eq!(cpu.registers.get_pc(), 0xABCD);
+        assert_eq!(cpu.registers.get_pc(), 0xCBCD);
 
         // jump if not Z
         mmu.write_byte(cpu.registers.get_pc(), 0xAD);
@@ -2218,7 +2396,7 @@ mod tests {
         cpu.registers.set_z_flag(true);
         let jp_nz_true = cpu.decode_opcode(&mut mmu, 0xC2);
         cpu.execute_instruction(&mut mmu, jp_nz_true);
-        assert_eq!(cpu.registers.get_pc(), 0xABCD + 2);
+        assert_eq!(cpu.registers.get_pc(), 0xCBCD + 2);
 
         mmu.write_byte(cpu.registers.get_pc(), 0xAD);
         mmu.write_byte(cpu.registers.get_pc() + 1, 0xDE);
@@ -2229,129 +2407,129 @@ mod tests {
 
         // jump if Z
         mmu.write_byte(cpu.registers.get_pc(), 0xEF);
-        mmu.write_byte(cpu.registers.get_pc() + 1, 0xBE);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xCE);
         let jp_z_false = cpu.decode_opcode(&mut mmu, 0xCA);
         cpu.execute_instruction(&mut mmu, jp_z_false);
         assert_eq!(cpu.registers.get_pc(), 0xDEAD + 2);
 
         mmu.write_byte(cpu.registers.get_pc(), 0xEF);
-        mmu.write_byte(cpu.registers.get_pc() + 1, 0xBE);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xCE);
         cpu.registers.set_z_flag(true);
         let jp_z_true = cpu.decode_opcode(&mut mmu, 0xCA);
         cpu.execute_instruction(&mut mmu, jp_z_true);
-        assert_eq!(cpu.registers.get_pc(), 0xBEEF);
+        assert_eq!(cpu.registers.get_pc(), 0xCEEF);
 
         // jump if not C
         mmu.write_byte(cpu.registers.get_pc(), 0x34);
-        mmu.write_byte(cpu.registers.get_pc() + 1, 0x12);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xD2);
         cpu.registers.set_c_flag(true);
         let jp_nc_true = cpu.decode_opcode(&mut mmu, 0xD2);
         cpu.execute_instruction(&mut mmu, jp_nc_true);
-        assert_eq!(cpu.registers.get_pc(), 0xBEEF + 2);
+        assert_eq!(cpu.registers.get_pc(), 0xCEEF + 2);
 
         mmu.write_byte(cpu.registers.get_pc(), 0x34);
-        mmu.write_byte(cpu.registers.get_pc() + 1, 0x12);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xD2);
         cpu.registers.set_c_flag(false);
         let jp_nc_false = cpu.decode_opcode(&mut mmu, 0xD2);
         cpu.execute_instruction(&mut mmu, jp_nc_false);
-        assert_eq!(cpu.registers.get_pc(), 0x1234);
+        assert_eq!(cpu.registers.get_pc(), 0xD234);
 
         // jump if C
         mmu.write_byte(cpu.registers.get_pc(), 0x89);
-        mmu.write_byte(cpu.registers.get_pc() + 1, 0x67);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xD7);
         let jp_c_false = cpu.decode_opcode(&mut mmu, 0xDA);
         cpu.execute_instruction(&mut mmu, jp_c_false);
-        assert_eq!(cpu.registers.get_pc(), 0x1234 + 2);
+        assert_eq!(cpu.registers.get_pc(), 0xD234 + 2);
 
         mmu.write_byte(cpu.registers.get_pc(), 0x89);
-        mmu.write_byte(cpu.registers.get_pc() + 1, 0x67);
+        mmu.write_byte(cpu.registers.get_pc() + 1, 0xD7);
         cpu.registers.set_c_flag(true);
         let jp_c_true = cpu.decode_opcode(&mut mmu, 0xDA);
         cpu.execute_instruction(&mut mmu, jp_c_true);
-        assert_eq!(cpu.registers.get_pc(), 0x6789);
+        assert_eq!(cpu.registers.get_pc(), 0xD789);
 
         // jump to (hl)
-        cpu.registers.set_hl(0x9007);
+        cpu.registers.set_hl(0xC007);
         let jphl = cpu.decode_opcode(&mut mmu, 0xE9);
         cpu.execute_instruction(&mut mmu, jphl);
-        assert_eq!(cpu.registers.get_pc(), 0x9007);
+        assert_eq!(cpu.registers.get_pc(), 0xC007);
 
         // jump to current address + n
         mmu.write_byte(cpu.registers.get_pc(), 0x7F);
         let jrn = cpu.decode_opcode(&mut mmu, 0x18);
         cpu.execute_instruction(&mut mmu, jrn);
-        assert_eq!(cpu.registers.get_pc(), 0x9007 + 1 + 0x7F);
+        assert_eq!(cpu.registers.get_pc(), 0xC007 + 1 + 0x7F);
 
         // jump to current address + n if not Z
-        cpu.registers.set_pc(0x1234);
+        cpu.registers.set_pc(0xD234);
         mmu.write_byte(cpu.registers.get_pc(), 0x7B);
         cpu.registers.set_z_flag(true);
         let jr_nz_true = cpu.decode_opcode(&mut mmu, 0x20);
         cpu.execute_instruction(&mut mmu, jr_nz_true);
-        assert_eq!(cpu.registers.get_pc(), 0x1234 + 1);
+        assert_eq!(cpu.registers.get_pc(), 0xD234 + 1);
 
-        cpu.registers.set_pc(0x1234);
+        cpu.registers.set_pc(0xD234);
         mmu.write_byte(cpu.registers.get_pc(), 0x7F);
         cpu.registers.set_z_flag(false);
         let jr_nz_false = cpu.decode_opcode(&mut mmu, 0x20);
         cpu.execute_instruction(&mut mmu, jr_nz_false);
-        assert_eq!(cpu.registers.get_pc(), 0x1234 + 1 + 0x7F);
+        assert_eq!(cpu.registers.get_pc(), 0xD234 + 1 + 0x7F);
 
         // jump to current address + n if Z
-        cpu.registers.set_pc(0x2345);
+        cpu.registers.set_pc(0xD345);
         mmu.write_byte(cpu.registers.get_pc(), 0x7F);
         let jr_z_false = cpu.decode_opcode(&mut mmu, 0x28);
         cpu.execute_instruction(&mut mmu, jr_z_false);
-        assert_eq!(cpu.registers.get_pc(), 0x2345 + 1);
+        assert_eq!(cpu.registers.get_pc(), 0xD345 + 1);
 
-        cpu.registers.set_pc(0x2345);
+        cpu.registers.set_pc(0xD345);
         cpu.registers.set_z_flag(true);
         let jr_z_true = cpu.decode_opcode(&mut mmu, 0x28);
         cpu.execute_instruction(&mut mmu, jr_z_true);
-        assert_eq!(cpu.registers.get_pc(), 0x2345 + 1 + 0x7F);
+        assert_eq!(cpu.registers.get_pc(), 0xD345 + 1 + 0x7F);
 
         // jump to current address + n if not C
-        cpu.registers.set_pc(0x3456);
+        cpu.registers.set_pc(0xD456);
         mmu.write_byte(cpu.registers.get_pc(), 0x7F);
         cpu.registers.set_c_flag(true);
         let jr_nc_true = cpu.decode_opcode(&mut mmu, 0x30);
         cpu.execute_instruction(&mut mmu, jr_nc_true);
-        assert_eq!(cpu.registers.get_pc(), 0x3456 + 1);
+        assert_eq!(cpu.registers.get_pc(), 0xD456 + 1);
 
-        cpu.registers.set_pc(0x3456);
+        cpu.registers.set_pc(0xD456);
         cpu.registers.set_c_flag(false);
         let jr_nc_false = cpu.decode_opcode(&mut mmu, 0x30);
         cpu.execute_instruction(&mut mmu, jr_nc_false);
-        assert_eq!(cpu.registers.get_pc(), 0x3456 + 1 + 0x7F);
+        assert_eq!(cpu.registers.get_pc(), 0xD456 + 1 + 0x7F);
 
         // jump to current address + n if C
-        cpu.registers.set_pc(0x4567);
+        cpu.registers.set_pc(0xD567);
         mmu.write_byte(cpu.registers.get_pc(), 0x7F);
         let jr_c_false = cpu.decode_opcode(&mut mmu, 0x38);
         cpu.execute_instruction(&mut mmu, jr_c_false);
-        assert_eq!(cpu.registers.get_pc(), 0x4567 + 1);
+        assert_eq!(cpu.registers.get_pc(), 0xD567 + 1);
 
-        cpu.registers.set_pc(0x4567);
+        cpu.registers.set_pc(0xD567);
         cpu.registers.set_c_flag(true);
         let jr_c_true = cpu.decode_opcode(&mut mmu, 0x38);
         cpu.execute_instruction(&mut mmu, jr_c_true);
-        assert_eq!(cpu.registers.get_pc(), 0x4567 + 1 + 0x7F);
+        assert_eq!(cpu.registers.get_pc(), 0xD567 + 1 + 0x7F);
     }
 
     #[test]
     fn cpu_call_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         // call
-        cpu.registers.set_pc(0xABCD);
-        cpu.registers.set_sp(0xFEEE);
+        cpu.registers.set_pc(0xCBCD);
+        cpu.registers.set_sp(0xFFFE);
         mmu.write_byte(cpu.registers.get_pc(), 0xAD);
         mmu.write_byte(cpu.registers.get_pc() + 1, 0xDE);
         let call = cpu.decode_opcode(&mut mmu, 0xCD);
         cpu.execute_instruction(&mut mmu, call);
         assert_eq!(cpu.registers.get_pc(), 0xDEAD);
-        assert_eq!(cpu.pop(&mut mmu), 0xABCD + 2);
+        assert_eq!(cpu.pop(&mut mmu), 0xCBCD + 2);
 
         // call if not Z
         cpu.registers.set_z_flag(true);
@@ -2369,54 +2547,54 @@ mod tests {
         assert_eq!(cpu.pop(&mut mmu), 0xDEDE + 2);
 
         // call if Z
-        cpu.registers.set_pc(0x1000);
+        cpu.registers.set_pc(0xC100);
         mmu.write_byte(cpu.registers.get_pc(), 0x34);
         mmu.write_byte(cpu.registers.get_pc() + 1, 0x12);
         let call_z_false = cpu.decode_opcode(&mut mmu, 0xCC);
         cpu.execute_instruction(&mut mmu, call_z_false);
-        assert_eq!(cpu.registers.get_pc(), 0x1000);
+        assert_eq!(cpu.registers.get_pc(), 0xC100);
 
         cpu.registers.set_z_flag(true);
         let call_z_true = cpu.decode_opcode(&mut mmu, 0xCC);
         cpu.execute_instruction(&mut mmu, call_z_true);
         assert_eq!(cpu.registers.get_pc(), 0x1234);
-        assert_eq!(cpu.pop(&mut mmu), 0x1000 + 2);
+        assert_eq!(cpu.pop(&mut mmu), 0xC100 + 2);
 
         // call if not C
-        cpu.registers.set_pc(0x2000);
+        cpu.registers.set_pc(0xC200);
         cpu.registers.set_c_flag(true);
         mmu.write_byte(cpu.registers.get_pc(), 0x45);
         mmu.write_byte(cpu.registers.get_pc() + 1, 0x23);
         let call_nc_true = cpu.decode_opcode(&mut mmu, 0xD4);
         cpu.execute_instruction(&mut mmu, call_nc_true);
-        assert_eq!(cpu.registers.get_pc(), 0x2000);
+        assert_eq!(cpu.registers.get_pc(), 0xC200);
 
         cpu.registers.set_c_flag(false);
         let call_nc_false = cpu.decode_opcode(&mut mmu, 0xD4);
         cpu.execute_instruction(&mut mmu, call_nc_false);
         assert_eq!(cpu.registers.get_pc(), 0x2345);
-        assert_eq!(cpu.pop(&mut mmu), 0x2000 + 2);
+        assert_eq!(cpu.pop(&mut mmu), 0xC200 + 2);
 
         // call if C
-        cpu.registers.set_pc(0x3000);
+        cpu.registers.set_pc(0xC300);
         mmu.write_byte(cpu.registers.get_pc(), 0x56);
         mmu.write_byte(cpu.registers.get_pc() + 1, 0x34);
         let call_c_false = cpu.decode_opcode(&mut mmu, 0xDC);
         cpu.execute_instruction(&mut mmu, call_c_false);
-        assert_eq!(cpu.registers.get_pc(), 0x3000);
+        assert_eq!(cpu.registers.get_pc(), 0xC300);
 
         cpu.registers.set_c_flag(true);
         let call_c_true = cpu.decode_opcode(&mut mmu, 0xDC);
         cpu.execute_instruction(&mut mmu, call_c_true);
         assert_eq!(cpu.registers.get_pc(), 0x3456);
-        assert_eq!(cpu.pop(&mut mmu), 0x3000 + 2);
+        assert_eq!(cpu.pop(&mut mmu), 0xC300 + 2);
     }
 
     #[test]
     fn cpu_restart_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
-        cpu.registers.set_sp(0xFEEE);
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        cpu.registers.set_sp(0xFFFE);
 
         cpu.registers.set_pc(0x1000);
         let rst_00 = cpu.decode_opcode(&mut mmu, 0xC7);
@@ -2469,11 +2647,11 @@ mod tests {
 
     #[test]
     fn cpu_ret_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
 
         // return
-        cpu.registers.set_sp(0xFEEE);
+        cpu.registers.set_sp(0xFFFE);
         cpu.push(&mut mmu, 0x1000);
         let ret = cpu.decode_opcode(&mut mmu, 0xC9);
         cpu.execute_instruction(&mut mmu, ret);
@@ -2528,7 +2706,7 @@ mod tests {
 
     /*#[test]
     fn cpu_bootstrap_test() {
-        let mut cpu = Cpu::new();
+        let mut cpu = test_cpu();
 
         let bootstrap = vec!{
             0x31, 0xFE, 0xFF, 0xAF, 0x21, 0xFF, 0x9F, 0x32, 0xCB, 0x7C, 0x20, 0xFB, 0x21, 0x26, 0xFF, 0x0E,
@@ -2558,18 +2736,18 @@ mod tests {
 
     #[test]
     fn cpu_cycles_test() {
-        let mut cpu = Cpu::new();
-        let mut mmu = Mmu::new();
-        cpu.registers.set_hl(0xABCD);
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        cpu.registers.set_hl(0xCBCD);
 
-        let test_opcode = |cpu: &mut Cpu, mmu: &mut Mmu, opcode: u8, expected_cycles: u8| {
+        let test_opcode = |cpu: &mut Cpu, mmu: &mut Mmu, opcode: u8, expected_cycles: u32| {
             cpu.cycles = 0;
+            cpu.registers.set_pc(PROGRAM_ADDRESS);
             mmu.write_byte(cpu.registers.get_pc(), opcode);
             cpu.step(mmu);
             assert_eq!(cpu.cycles, expected_cycles);
         };
 
-        // missing 0x27, 0x76, 0x10, 0xF3, 0xFB, 0xD9
         let opcodes_4_cycles = vec![0x7F, 0x78, 0x79, 0x7A, 0x7B, 0x7C, 0x7D, 0x40, 0x41, 0x42,
             0x43, 0x44, 0x45, 0x48, 0x49, 0x4A, 0x4B, 0x4C, 0x4D, 0x50, 0x51, 0x52, 0x53, 0x54, 0x55,
             0x58, 0x59, 0x5A, 0x5B, 0x5C, 0x5D, 0x60, 0x61, 0x62, 0x63, 0x64, 0x65, 0x68, 0x69, 0x6A,
@@ -2579,14 +2757,14 @@ mod tests {
             0xA7, 0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xB7, 0xB0, 0xB1, 0xB2, 0xB3, 0xB4, 0xB5, 0xAF,
             0xA8, 0xA9, 0xAA, 0xAB, 0xAC, 0xAD, 0xBF, 0xB8, 0xB9, 0xBA, 0xBB, 0xBC, 0xBD, 0x3C, 0x04,
             0x0C, 0x14, 0x1C, 0x24, 0x2C, 0x3D, 0x05, 0x0D, 0x15, 0x1D, 0x25, 0x2D, 0x2F, 0x3F, 0x37,
-            0x00, 0x07, 0x17, 0x0F, 0x1F, 0xE9];
+            0x00, 0x07, 0x17, 0x0F, 0x1F, 0xE9, 0xF3, 0xFB, 0x27];
         let opcodes_8_cycles = vec![0x06, 0x0E, 0x16, 0x1E, 0x26, 0x2E, 0x7E, 0x46, 0x4E, 0x56, 0x5E,
             0x66, 0x6E, 0x70, 0x71, 0x72, 0x73, 0x74, 0x75, 0x0A, 0x1A, 0x7E, 0x3E, 0x02, 0x12, 0x77,
             0xF2, 0xE2, 0x3A, 0x32, 0x2A, 0x22, 0xF9, 0x86, 0xC6, 0x8E, 0xCE, 0x96, 0xD6, 0x9E, 0xA6,
-            0xE6, 0xB6, 0xF6, 0xAE, 0xEE, 0xBE, 0xFE, 0x09, 0x19, 0x29, 0x39, 0x03, 0x13, 0x23, 0x33,
+            0xE6, 0xB6, 0xF6, 0xAE, 0xEE, 0xBE, 0xFE, 0xDE, 0x09, 0x19, 0x29, 0x39, 0x03, 0x13, 0x23, 0x33,
             0x0B, 0x1B, 0x2B, 0x3B, 0x18, 0x20, 0x28, 0x30, 0x38];
         let opcodes_12_cycles = vec![0x36, 0xE0, 0xF0, 0x01, 0x11, 0x21, 0x31, 0xF8, 0xF1, 0xC1, 0xD1,
-            0xE1, 0x34, 0x35, 0xC3, 0xC2, 0xCA, 0xD2, 0xDA, 0xC9];
+            0xE1, 0x34, 0x35, 0xC3, 0xC2, 0xCA, 0xD2, 0xDA, 0xC9, 0xD9];
         let opcodes_16_cycles = vec![0xFA, 0xEA, 0xF5, 0xC5, 0xD5, 0xE5, 0xE8];
         let opcodes_32_cycles = vec![0xC7, 0xCF, 0xD7, 0xDF, 0xE7, 0xEF, 0xF7, 0xFF];
 
@@ -2609,7 +2787,7 @@ mod tests {
         test_opcode(&mut cpu, &mut mmu, 0x08, 20);
         test_opcode(&mut cpu, &mut mmu, 0xCD, 24);
 
-        cpu.registers.set_sp(0xABCD);
+        cpu.registers.set_sp(0xFFFE);
         for opcode in opcodes_32_cycles {
             test_opcode(&mut cpu, &mut mmu, opcode, 32);
         }
@@ -2655,6 +2833,277 @@ mod tests {
         test_opcode(&mut cpu, &mut mmu, 0xD8, 4);
         cpu.registers.set_c_flag(true);
         test_opcode(&mut cpu, &mut mmu, 0xD8, 12);
+
+        // halt & stop
+        test_opcode(&mut cpu, &mut mmu, 0x76, 4);
+        cpu.state = CpuState::Running;
+        test_opcode(&mut cpu, &mut mmu, 0x10, 4);
+    }
+
+    #[test]
+    fn cpu_interrupt_dispatch_test() {
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        cpu.registers.set_pc(0xC234);
+        cpu.registers.set_sp(0xFFFE);
+        cpu.ime = true;
+
+        // interrupt requested but not enabled
+        mmu.request_interrupt(Interrupt::Timer);
+        mmu.write_byte(0xC234, 0x00);
+        assert_eq!(cpu.step(&mut mmu), 4);
+        assert_eq!(cpu.registers.get_pc(), 0xC235);
+
+        // the highest priority interrupt is serviced first
+        mmu.write_byte(0xFFFF, 0b0001_1111);
+        mmu.request_interrupt(Interrupt::Joypad);
+        assert_eq!(cpu.step(&mut mmu), 20);
+        assert_eq!(cpu.registers.get_pc(), 0x0050);
+        assert_eq!(cpu.registers.get_sp(), 0xFFFC);
+        assert_eq!(cpu.pop(&mut mmu), 0xC235);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1111_0000);
+        assert!(!cpu.ime);
+
+        // with IME cleared the remaining interrupt stays pending
+        mmu.write_byte(0x0050, 0x00);
+        assert_eq!(cpu.step(&mut mmu), 4);
+        assert_eq!(cpu.registers.get_pc(), 0x0051);
+
+        cpu.ime = true;
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.registers.get_pc(), 0x0060);
+        assert_eq!(mmu.pending_interrupts(), 0b0000_0000);
+    }
+
+    #[test]
+    fn cpu_ei_di_test() {
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        cpu.registers.set_pc(0xC100);
+        cpu.registers.set_sp(0xFFFE);
+        mmu.write_byte(0xFFFF, 0b0000_0001);
+        mmu.request_interrupt(Interrupt::VBlank);
+
+        // EI, NOP, NOP: the interrupt is serviced after the instruction following EI
+        mmu.write_byte(0xC100, 0xFB);
+        mmu.write_byte(0xC101, 0x00);
+        mmu.write_byte(0xC102, 0x00);
+        cpu.step(&mut mmu);
+        assert!(!cpu.ime);
+        cpu.step(&mut mmu);
+        assert!(cpu.ime);
+        assert_eq!(cpu.registers.get_pc(), 0xC102);
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.registers.get_pc(), 0x0040);
+
+        // EI, DI: interrupts never get enabled
+        mmu.request_interrupt(Interrupt::VBlank);
+        cpu.registers.set_pc(0xC200);
+        mmu.write_byte(0xC200, 0xFB);
+        mmu.write_byte(0xC201, 0xF3);
+        mmu.write_byte(0xC202, 0x00);
+        cpu.step(&mut mmu);
+        cpu.step(&mut mmu);
+        cpu.step(&mut mmu);
+        assert!(!cpu.ime);
+        assert_eq!(cpu.registers.get_pc(), 0xC203);
     }
-}
 
+    #[test]
+    fn cpu_reti_test() {
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        cpu.registers.set_sp(0xFFFE);
+        cpu.push(&mut mmu, 0x4000);
+
+        let reti = cpu.decode_opcode(&mut mmu, 0xD9);
+        cpu.execute_instruction(&mut mmu, reti);
+        assert_eq!(cpu.registers.get_pc(), 0x4000);
+        assert_eq!(cpu.registers.get_sp(), 0xFFFE);
+        assert!(cpu.ime);
+    }
+
+    #[test]
+    fn cpu_halt_test() {
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        cpu.registers.set_pc(0xC100);
+        cpu.registers.set_sp(0xFFFE);
+        mmu.write_byte(0xC100, 0x76);
+        mmu.write_byte(0xC101, 0x00);
+        mmu.write_byte(0xFFFF, 0b0000_0100);
+
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.state(), CpuState::Halted);
+        assert_eq!(cpu.step(&mut mmu), 4);
+        assert_eq!(cpu.registers.get_pc(), 0xC101);
+
+        // an interrupt disabled in IE does not wake the CPU up
+        mmu.request_interrupt(Interrupt::VBlank);
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.state(), CpuState::Halted);
+
+        // with IME cleared the CPU resumes execution after HALT
+        mmu.request_interrupt(Interrupt::Timer);
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.state(), CpuState::Running);
+        assert_eq!(cpu.registers.get_pc(), 0xC102);
+
+        // with IME set the interrupt is serviced
+        mmu.write_byte(0xFF0F, 0x0);
+        cpu.registers.set_pc(0xC100);
+        cpu.ime = true;
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.state(), CpuState::Halted);
+        mmu.request_interrupt(Interrupt::Timer);
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.state(), CpuState::Running);
+        assert_eq!(cpu.registers.get_pc(), 0x0050);
+        assert_eq!(cpu.pop(&mut mmu), 0xC101);
+    }
+
+    #[test]
+    fn cpu_halt_bug_test() {
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        cpu.registers.set_pc(0xC100);
+        // HALT, INC A, NOP
+        mmu.write_byte(0xC100, 0x76);
+        mmu.write_byte(0xC101, 0x3C);
+        mmu.write_byte(0xC102, 0x00);
+        mmu.write_byte(0xFFFF, 0b0000_0001);
+        mmu.request_interrupt(Interrupt::VBlank);
+
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.state(), CpuState::Running);
+        assert_eq!(cpu.registers.get_pc(), 0xC101);
+
+        // INC A is executed twice
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.registers.get_pc(), 0xC101);
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.registers.get_pc(), 0xC102);
+        assert_eq!(cpu.registers.get_a(), 0x2);
+    }
+
+    #[test]
+    fn cpu_stop_test() {
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+        cpu.registers.set_pc(0xC100);
+        mmu.write_byte(0xC100, 0x10);
+        mmu.write_byte(0xC101, 0x00);
+        mmu.write_byte(0xC102, 0x00);
+        mmu.write_byte(0xFF00, 0x10);
+        mmu.write_byte(0xFF04, 0xAB);
+
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.state(), CpuState::Stopped);
+        assert_eq!(cpu.registers.get_pc(), 0xC102);
+        assert_eq!(mmu.read_byte(0xFF04), 0x0);
+
+        assert_eq!(cpu.step(&mut mmu), 4);
+        assert_eq!(cpu.state(), CpuState::Stopped);
+
+        // pressing a button resumes execution
+        mmu.set_buttons(Button::A.mask());
+        cpu.step(&mut mmu);
+        assert_eq!(cpu.state(), CpuState::Running);
+        assert_eq!(cpu.registers.get_pc(), 0xC103);
+    }
+
+    #[test]
+    fn cpu_boot_rom_test() {
+        let mut cpu = Cpu::new();
+        let mut boot_rom = vec![0x00; 0x100];
+        // LD A, 0x01; LDH (0x50), A
+        boot_rom[0xFC..].copy_from_slice(&[0x3E, 0x01, 0xE0, 0x50]);
+        let mut mmu = Mmu::with_boot_rom(test_cartridge(), BootRom::from_bytes(boot_rom).unwrap());
+
+        while cpu.registers.get_pc() < 0x0100 {
+            cpu.step(&mut mmu);
+        }
+        assert_eq!(cpu.registers.get_pc(), 0x0100);
+        assert_eq!(mmu.read_byte(0x00FC), 0x00);
+    }
+
+    #[test]
+    fn cpu_skip_boot_test() {
+        let mut cpu = Cpu::new();
+        cpu.skip_boot();
+        assert_eq!(cpu.registers.get_af(), 0x01B0);
+        assert_eq!(cpu.registers.get_bc(), 0x0013);
+        assert_eq!(cpu.registers.get_de(), 0x00D8);
+        assert_eq!(cpu.registers.get_hl(), 0x014D);
+        assert_eq!(cpu.registers.get_sp(), 0xFFFE);
+        assert_eq!(cpu.registers.get_pc(), 0x0100);
+    }
+
+    #[test]
+    fn cpu_daa_test() {
+        let mut cpu = test_cpu();
+        let mut mmu = Mmu::new(test_cartridge());
+
+        let mut test_daa = |a: u8, flags: u8, expected_a: u8, expected_flags: u8| {
+            cpu.registers.set_a(a);
+            cpu.registers.set_f(flags);
+            let daa = cpu.decode_opcode(&mut mmu, 0x27);
+            cpu.execute_instruction(&mut mmu, daa);
+            assert_eq!(cpu.registers.get_a(), expected_a);
+            assert_eq!(cpu.registers.get_f(), expected_flags);
+        };
+
+        // after addition
+        test_daa(0x0A, 0b0000_0000, 0x10, 0b0000_0000);
+        test_daa(0x9A, 0b0000_0000, 0x00, 0b1001_0000);
+        test_daa(0x12, 0b0010_0000, 0x18, 0b0000_0000);
+        test_daa(0x45, 0b0001_0000, 0xA5, 0b0001_0000);
+        test_daa(0xA0, 0b0000_0000, 0x00, 0b1001_0000);
+        // after subtraction
+        test_daa(0x0F, 0b0110_0000, 0x09, 0b0100_0000);
+        test_daa(0xF0, 0b0101_0000, 0x90, 0b0101_0000);
+        test_daa(0x66, 0b0111_0000, 0x00, 0b1101_0000);
+        test_daa(0x42, 0b0100_0000, 0x42, 0b0100_0000);
+    }
+
+    #[test]
+    fn cpu_illegal_opcode_test() {
+        let illegal_opcodes = vec![0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+
+        for opcode in illegal_opcodes {
+            let mut cpu = test_cpu();
+            let mut mmu = Mmu::new(test_cartridge());
+            cpu.registers.set_pc(0xC100);
+            mmu.write_byte(0xC100, opcode);
+            mmu.write_byte(0xC101, 0x00);
+            mmu.write_byte(0xFFFF, 0b0001_1111);
+            cpu.ime = true;
+
+            cpu.step(&mut mmu);
+            assert_eq!(cpu.state(), CpuState::Locked(opcode));
+
+            // neither instructions nor interrupts are executed anymore
+            mmu.request_interrupt(Interrupt::VBlank);
+            assert_eq!(cpu.step(&mut mmu), 4);
+            assert_eq!(cpu.registers.get_pc(), 0xC101);
+        }
+    }
+
+    #[test]
+    fn cpu_load_a_from_0xff00_plus_c_test() {
+        let mut cpu = Cpu::new();
+        let mut mmu = Mmu::new(test_cartridge());
+
+        // LD (C),A and LD A,(C) address the same byte of the 0xFF00 page
+        cpu.registers.set_a(0x5A);
+        cpu.registers.set_c(0x85);
+        let store = cpu.decode_opcode(&mut mmu, 0xE2);
+        cpu.execute_instruction(&mut mmu, store);
+        assert_eq!(mmu.read_byte(0xFF85), 0x5A);
+
+        cpu.registers.set_a(0x00);
+        let load = cpu.decode_opcode(&mut mmu, 0xF2);
+        cpu.execute_instruction(&mut mmu, load);
+        assert_eq!(cpu.registers.get_a(), 0x5A);
+    }
+}
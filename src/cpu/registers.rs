@@ -35,6 +35,22 @@ impl Registers {
         }
     }
 
+    // values left behind by the DMG boot ROM, H and C are set for any cartridge with a non-zero header checksum
+    pub fn post_boot() -> Registers {
+        Registers {
+            a: 0x01,
+            b: 0x00,
+            c: 0x13,
+            d: 0x00,
+            e: 0xD8,
+            h: 0x01,
+            l: 0x4D,
+            f: FlagRegister::from(0xB0),
+            pc: 0x0100,
+            sp: 0xFFFE,
+        }
+    }
+
     pub fn get_a(&self) -> u8 {
         self.a
     }
@@ -91,10 +107,12 @@ impl Registers {
         self.l = value;
     }
 
+    #[cfg(test)]
     pub fn get_f(&self) -> u8 {
         u8::from(self.f)
     }
 
+    #[cfg(test)]
     pub fn set_f(&mut self, value: u8) {
         self.f = FlagRegister::from(value);
     }
@@ -137,13 +155,13 @@ impl Registers {
 
     pub fn increment_hl(&mut self) -> u16 {
         let previous_hl = self.get_hl();
-        self.set_hl(previous_hl + 1);
+        self.set_hl(previous_hl.wrapping_add(1));
         previous_hl
     }
 
     pub fn decrement_hl(&mut self) -> u16 {
         let previous_hl = self.get_hl();
-        self.set_hl(previous_hl - 1);
+        self.set_hl(previous_hl.wrapping_sub(1));
         previous_hl
     }
 
@@ -159,10 +177,18 @@ impl Registers {
         self.f.n = state;
     }
 
+    pub fn get_n_flag(&self) -> bool {
+        self.f.n
+    }
+
     pub fn set_h_flag(&mut self, state: bool) {
         self.f.h = state;
     }
 
+    pub fn get_h_flag(&self) -> bool {
+        self.f.h
+    }
+
     pub fn set_c_flag(&mut self, state: bool) {
         self.f.c = state;
     }
@@ -177,7 +203,7 @@ impl Registers {
 
     pub fn get_and_increment_pc(&mut self) -> u16 {
         let previous_pc = self.pc;
-        self.pc += 1;
+        self.pc = self.pc.wrapping_add(1);
         previous_pc
     }
 
@@ -194,11 +220,11 @@ impl Registers {
     }
 
     pub fn decrement_sp(&mut self) {
-        self.sp -= 1;
+        self.sp = self.sp.wrapping_sub(1);
     }
 
     pub fn increment_sp(&mut self) {
-        self.sp += 1;
+        self.sp = self.sp.wrapping_add(1);
     }
 }
 
@@ -1,15 +0,0 @@
-use super::Mmu;
-
-pub struct Gpu {
-
-}
-
-impl Gpu {
-    pub fn new() -> Gpu {
-        Gpu {}
-    }
-
-    pub fn step(&self, mmu: &mut Mmu) {
-
-    }
-}
\ No newline at end of file
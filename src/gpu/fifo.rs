@@ -0,0 +1,295 @@
+use super::{sprite::Sprite, Gpu, SCREEN_WIDTH};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FetcherStep {
+    TileIndex,
+    TileDataLow,
+    TileDataHigh,
+    Push,
+}
+
+// fetches a row of 8 background or window pixels, every step but the push takes 2 dots
+struct Fetcher {
+    step: FetcherStep,
+    dots: u8,
+    tile_x: u8,
+    tile_index: u8,
+    low: u8,
+    high: u8,
+    window: bool,
+}
+
+impl Fetcher {
+    fn new(window: bool) -> Fetcher {
+        Fetcher {
+            step: FetcherStep::TileIndex,
+            dots: 0,
+            tile_x: 0,
+            tile_index: 0,
+            low: 0,
+            high: 0,
+            window,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ObjectPixel {
+    color: u8,
+    obp1: bool,
+    behind_background: bool,
+}
+
+pub struct PixelFifo {
+    background: VecDeque<u8>,
+    objects: VecDeque<ObjectPixel>,
+    fetcher: Fetcher,
+    // sprites selected by the OAM scan that were not fetched yet
+    sprites: Vec<Sprite>,
+    sprite_fetch: Option<(Sprite, u8)>,
+    // only the first object within a background tile waits for the fetcher
+    stalled_tile: Option<u8>,
+    startup_dots: u8,
+    discard: u8,
+    x: u8,
+    window_active: bool,
+}
+
+impl PixelFifo {
+    // the first tile fetch of a line is thrown away
+    const STARTUP_DOTS: u8 = 6;
+    const SPRITE_FETCH_DOTS: u8 = 6;
+
+    pub fn new() -> PixelFifo {
+        PixelFifo {
+            background: VecDeque::with_capacity(16),
+            objects: VecDeque::with_capacity(8),
+            fetcher: Fetcher::new(false),
+            sprites: Vec::with_capacity(Sprite::MAX_PER_LINE),
+            sprite_fetch: None,
+            stalled_tile: None,
+            startup_dots: 0,
+            discard: 0,
+            x: 0,
+            window_active: false,
+        }
+    }
+
+    pub fn line_done(&self) -> bool {
+        usize::from(self.x) == SCREEN_WIDTH
+    }
+
+    pub fn window_active(&self) -> bool {
+        self.window_active
+    }
+}
+
+impl Gpu {
+    pub(super) fn start_drawing(&mut self) {
+        let sprites = self.scan_oam(self.ly);
+        let fifo = &mut self.fifo;
+        fifo.background.clear();
+        fifo.objects.clear();
+        fifo.fetcher = Fetcher::new(false);
+        fifo.sprites = sprites;
+        fifo.sprite_fetch = None;
+        fifo.stalled_tile = None;
+        fifo.startup_dots = PixelFifo::STARTUP_DOTS;
+        // fine scroll is applied by dropping the first pixels of the line
+        fifo.discard = self.scx & 0x7;
+        fifo.x = 0;
+        fifo.window_active = false;
+
+        // the window becomes visible once LY matched WY at any point of the frame
+        if self.ly == self.wy {
+            self.window_y_triggered = true;
+        }
+        let full_line = self.window_full_line;
+        self.window_full_line = false;
+        if self.window_enabled() {
+            match self.wx {
+                _ if full_line => self.start_window(0),
+                // the window start collides with the fine scroll of the background
+                0 => self.start_window(7 + (self.scx & 0x7)),
+                1..=6 => self.start_window(7 - self.wx),
+                // the window misses this line and covers all of the next one instead
+                166 => self.window_full_line = true,
+                _ => (),
+            }
+        }
+    }
+
+    // on DMG a disabled background also hides the window
+    fn window_enabled(&self) -> bool {
+        self.lcdc & Gpu::WINDOW_ENABLE != 0 && self.lcdc & Gpu::BG_ENABLE != 0 && self.window_y_triggered
+    }
+
+    fn start_window(&mut self, discard: u8) {
+        let fifo = &mut self.fifo;
+        fifo.background.clear();
+        fifo.fetcher = Fetcher::new(true);
+        fifo.discard = discard;
+        fifo.window_active = true;
+    }
+
+    pub(super) fn draw_dot(&mut self) {
+        if self.fifo.startup_dots > 0 {
+            self.fifo.startup_dots -= 1;
+            return;
+        }
+
+        // the background fetcher keeps going while an object fetch stalls the pixel output
+        if let Some((sprite, dots)) = self.fifo.sprite_fetch {
+            self.step_fetcher();
+            if dots > 1 {
+                self.fifo.sprite_fetch = Some((sprite, dots - 1));
+            } else {
+                self.fifo.sprite_fetch = None;
+                self.merge_sprite(sprite);
+            }
+            return;
+        }
+
+        self.step_fetcher();
+        if self.fifo.background.is_empty() {
+            return;
+        }
+
+        if !self.fifo.window_active && self.window_enabled() && (7..=165).contains(&self.wx) && self.fifo.x + 7 == self.wx {
+            self.start_window(0);
+            self.step_fetcher();
+            return;
+        }
+
+        if self.lcdc & Gpu::OBJ_ENABLE != 0 {
+            // the sprite starting furthest left goes first, so it wins overlaps as DMG priority requires
+            let x = self.fifo.x + 8;
+            let next_sprite = (0..self.fifo.sprites.len())
+                .filter(|index| self.fifo.sprites[*index].x <= x)
+                .min_by_key(|index| (self.fifo.sprites[*index].x, self.fifo.sprites[*index].oam_index));
+            if let Some(index) = next_sprite {
+                let sprite = self.fifo.sprites.remove(index);
+                let stall = self.sprite_stall(&sprite);
+                self.fifo.sprite_fetch = Some((sprite, stall - 1));
+                return;
+            }
+        }
+
+        let color = self.fifo.background.pop_front().unwrap();
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return;
+        }
+        let object = self.fifo.objects.pop_front();
+        self.output_pixel(color, object);
+    }
+
+    fn output_pixel(&mut self, color: u8, object: Option<ObjectPixel>) {
+        let background_enabled = self.lcdc & Gpu::BG_ENABLE != 0;
+        // on DMG a disabled background is plain white and never covers objects
+        let color = if background_enabled { color } else { 0 };
+        let mut shade = if background_enabled { Gpu::shade(self.bgp, color) } else { 0 };
+
+        if let Some(object) = object {
+            // background colors 1-3 cover objects with the priority bit, color 0 of an object is transparent
+            if object.color != 0 && self.lcdc & Gpu::OBJ_ENABLE != 0 && !(object.behind_background && color != 0) {
+                let palette = if object.obp1 { self.obp1 } else { self.obp0 };
+                shade = Gpu::shade(palette, object.color);
+            }
+        }
+
+        self.framebuffer[usize::from(self.ly) * SCREEN_WIDTH + usize::from(self.fifo.x)] = shade;
+        self.fifo.x += 1;
+    }
+
+    fn step_fetcher(&mut self) {
+        let step = self.fifo.fetcher.step;
+        if step == FetcherStep::Push {
+            // pixels are only pushed into an empty FIFO
+            if self.fifo.background.is_empty() {
+                let Fetcher { low, high, .. } = self.fifo.fetcher;
+                for bit in (0..8).rev() {
+                    self.fifo.background.push_back(((high >> bit) & 0x1) << 1 | ((low >> bit) & 0x1));
+                }
+                self.fifo.fetcher.tile_x = self.fifo.fetcher.tile_x.wrapping_add(1);
+                self.fifo.fetcher.step = FetcherStep::TileIndex;
+            }
+            return;
+        }
+
+        self.fifo.fetcher.dots += 1;
+        if self.fifo.fetcher.dots < 2 {
+            return;
+        }
+        self.fifo.fetcher.dots = 0;
+
+        let fetcher = &self.fifo.fetcher;
+        // scroll registers and LCDC are read at every fetch, so they can change mid line
+        let (tile_map_bit, tile_x, y) = if fetcher.window {
+            (Gpu::WINDOW_TILE_MAP, fetcher.tile_x, self.window_line)
+        } else {
+            (Gpu::BG_TILE_MAP, (self.scx / 8).wrapping_add(fetcher.tile_x), self.scy.wrapping_add(self.ly))
+        };
+        match step {
+            FetcherStep::TileIndex => {
+                let tile_map = if self.lcdc & tile_map_bit != 0 { 0x9C00 } else { 0x9800 };
+                let address = tile_map + u16::from(y / 8) * 32 + u16::from(tile_x & 0x1F);
+                self.fifo.fetcher.tile_index = self.vram.read_byte(address - Gpu::VRAM_START);
+                self.fifo.fetcher.step = FetcherStep::TileDataLow;
+            }
+            FetcherStep::TileDataLow => {
+                let address = self.tile_address(fetcher.tile_index) + u16::from(y % 8) * 2;
+                self.fifo.fetcher.low = self.vram.read_byte(address - Gpu::VRAM_START);
+                self.fifo.fetcher.step = FetcherStep::TileDataHigh;
+            }
+            FetcherStep::TileDataHigh => {
+                let address = self.tile_address(fetcher.tile_index) + u16::from(y % 8) * 2 + 1;
+                self.fifo.fetcher.high = self.vram.read_byte(address - Gpu::VRAM_START);
+                self.fifo.fetcher.step = FetcherStep::Push;
+            }
+            FetcherStep::Push => unreachable!(),
+        }
+    }
+
+    // fetching an object takes 6 dots, plus up to 5 more to let the background fetcher finish the tile under it
+    fn sprite_stall(&mut self, sprite: &Sprite) -> u8 {
+        let tile = (sprite.x.wrapping_add(self.scx)) / 8;
+        if self.fifo.stalled_tile == Some(tile) {
+            return PixelFifo::SPRITE_FETCH_DOTS;
+        }
+        self.fifo.stalled_tile = Some(tile);
+        PixelFifo::SPRITE_FETCH_DOTS + 5 - ((sprite.x.wrapping_add(self.scx)) % 8).min(5)
+    }
+
+    fn merge_sprite(&mut self, sprite: Sprite) {
+        // the OBJ size can change between the OAM scan and the fetch, the row then wraps within the new height
+        let height = self.sprite_height();
+        let mut row = (self.ly + 16 - sprite.y) & (height - 1);
+        if sprite.y_flip() {
+            row = height - 1 - row;
+        }
+        // 8x16 sprites ignore the lowest bit of the tile index, rows 8-15 come from the next tile
+        let tile = if height == 16 { sprite.tile & 0xFE } else { sprite.tile };
+        let address = u16::from(tile) * 16 + u16::from(row) * 2;
+        let low = self.vram.read_byte(address);
+        let high = self.vram.read_byte(address + 1);
+
+        // pixels left of the screen edge are dropped
+        let hidden = 8u8.saturating_sub(sprite.x);
+        for (slot, pixel) in (hidden..8).enumerate() {
+            let bit = if sprite.x_flip() { pixel } else { 7 - pixel };
+            let object = ObjectPixel {
+                color: ((high >> bit) & 0x1) << 1 | ((low >> bit) & 0x1),
+                obp1: sprite.uses_obp1(),
+                behind_background: sprite.behind_background(),
+            };
+            // objects already in the FIFO win, unless they are transparent at that pixel
+            match self.fifo.objects.get_mut(slot) {
+                Some(queued) if queued.color == 0 => *queued = object,
+                Some(_) => (),
+                None => self.fifo.objects.push_back(object),
+            }
+        }
+    }
+}
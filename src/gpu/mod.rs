@@ -0,0 +1,820 @@
+mod fifo;
+mod sprite;
+
+use super::mmu::{Interrupt, Memory};
+use fifo::PixelFifo;
+use sprite::Sprite;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+pub struct Gpu {
+    vram: Memory,
+    oam: Memory,
+    lcdc: u8,
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+    mode: Mode,
+    // dot within the current line, or within the current frame while the LCD is off
+    dots: u32,
+    stat_line: bool,
+    // the window keeps its own line counter, it only advances on lines where the window was drawn
+    window_line: u8,
+    window_y_triggered: bool,
+    window_full_line: bool,
+    fifo: PixelFifo,
+    // shades from 0 (white) to 3 (black)
+    framebuffer: Vec<u8>,
+    frame_ready: bool,
+}
+
+impl Gpu {
+    const VRAM_SIZE: usize = 0x2000;
+    const OAM_SIZE: usize = 0xA0;
+
+    const VRAM_START: u16 = 0x8000;
+    const OAM_START: u16 = 0xFE00;
+
+    const LCDC_ADDRESS: u16 = 0xFF40;
+    const STAT_ADDRESS: u16 = 0xFF41;
+    const SCY_ADDRESS: u16 = 0xFF42;
+    const SCX_ADDRESS: u16 = 0xFF43;
+    const LY_ADDRESS: u16 = 0xFF44;
+    const LYC_ADDRESS: u16 = 0xFF45;
+    const BGP_ADDRESS: u16 = 0xFF47;
+    const OBP0_ADDRESS: u16 = 0xFF48;
+    const OBP1_ADDRESS: u16 = 0xFF49;
+    const WY_ADDRESS: u16 = 0xFF4A;
+    const WX_ADDRESS: u16 = 0xFF4B;
+
+    const LCD_ENABLE: u8 = 0b1000_0000;
+    const WINDOW_TILE_MAP: u8 = 0b0100_0000;
+    const WINDOW_ENABLE: u8 = 0b0010_0000;
+    const BG_TILE_MAP: u8 = 0b0000_1000;
+    const OBJ_SIZE: u8 = 0b0000_0100;
+    const OBJ_ENABLE: u8 = 0b0000_0010;
+    const TILE_DATA: u8 = 0b0001_0000;
+    const BG_ENABLE: u8 = 0b0000_0001;
+
+    const HBLANK_INTERRUPT: u8 = 0b0000_1000;
+    const VBLANK_INTERRUPT: u8 = 0b0001_0000;
+    const OAM_INTERRUPT: u8 = 0b0010_0000;
+    const LYC_INTERRUPT: u8 = 0b0100_0000;
+    const LYC_EQUAL: u8 = 0b0000_0100;
+
+    const OAM_SCAN_DOTS: u32 = 80;
+    const DOTS_PER_LINE: u32 = 456;
+    const LINES_PER_FRAME: u8 = 154;
+    const FRAME_DOTS: u32 = Gpu::DOTS_PER_LINE * Gpu::LINES_PER_FRAME as u32;
+    const SPRITE_COUNT: u8 = 40;
+
+    pub fn new() -> Gpu {
+        Gpu {
+            vram: Memory::new(Gpu::VRAM_SIZE),
+            oam: Memory::new(Gpu::OAM_SIZE),
+            lcdc: 0x0,
+            stat: 0x0,
+            scy: 0x0,
+            scx: 0x0,
+            ly: 0x0,
+            lyc: 0x0,
+            bgp: 0x0,
+            obp0: 0x0,
+            obp1: 0x0,
+            wy: 0x0,
+            wx: 0x0,
+            mode: Mode::HBlank,
+            dots: 0,
+            stat_line: false,
+            window_line: 0,
+            window_y_triggered: false,
+            window_full_line: false,
+            fifo: PixelFifo::new(),
+            framebuffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frame_ready: false,
+        }
+    }
+
+    // registers as the DMG boot ROM leaves them, the LCD is on and about to start a new frame
+    pub fn skip_boot(&mut self) {
+        self.lcdc = 0x91;
+        self.bgp = 0xFC;
+        self.obp0 = 0xFF;
+        self.obp1 = 0xFF;
+        self.ly = Gpu::LINES_PER_FRAME - 1;
+        self.dots = 400;
+        self.mode = Mode::VBlank;
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0x9FFF => self.vram.read_byte(address - Gpu::VRAM_START),
+            0xFE00..=0xFE9F => self.oam.read_byte(address - Gpu::OAM_START),
+            Gpu::LCDC_ADDRESS => self.lcdc,
+            Gpu::STAT_ADDRESS => 0b1000_0000 | self.stat | self.lyc_equal() | self.mode as u8,
+            Gpu::SCY_ADDRESS => self.scy,
+            Gpu::SCX_ADDRESS => self.scx,
+            Gpu::LY_ADDRESS => self.ly_register(),
+            Gpu::LYC_ADDRESS => self.lyc,
+            Gpu::BGP_ADDRESS => self.bgp,
+            Gpu::OBP0_ADDRESS => self.obp0,
+            Gpu::OBP1_ADDRESS => self.obp1,
+            Gpu::WY_ADDRESS => self.wy,
+            Gpu::WX_ADDRESS => self.wx,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        match address {
+            0x8000..=0x9FFF => self.vram.write_byte(address - Gpu::VRAM_START, value),
+            0xFE00..=0xFE9F => self.oam.write_byte(address - Gpu::OAM_START, value),
+            Gpu::LCDC_ADDRESS => self.write_lcdc(value),
+            // only the interrupt select bits are writable
+            Gpu::STAT_ADDRESS => self.stat = value & 0b0111_1000,
+            Gpu::SCY_ADDRESS => self.scy = value,
+            Gpu::SCX_ADDRESS => self.scx = value,
+            Gpu::LY_ADDRESS => (),
+            Gpu::LYC_ADDRESS => self.lyc = value,
+            Gpu::BGP_ADDRESS => self.bgp = value,
+            Gpu::OBP0_ADDRESS => self.obp0 = value,
+            Gpu::OBP1_ADDRESS => self.obp1 = value,
+            Gpu::WY_ADDRESS => self.wy = value,
+            Gpu::WX_ADDRESS => self.wx = value,
+            _ => (),
+        }
+    }
+
+    fn write_lcdc(&mut self, value: u8) {
+        let enabled = self.lcdc & Gpu::LCD_ENABLE != 0;
+        self.lcdc = value;
+        // switching the LCD on restarts the frame from the first line, a disabled LCD stays in HBlank
+        if enabled != (value & Gpu::LCD_ENABLE != 0) {
+            self.ly = 0;
+            self.dots = 0;
+            self.mode = if enabled { Mode::HBlank } else { Mode::OamScan };
+            self.stat_line = false;
+            self.start_frame();
+        }
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.lcdc & Gpu::LCD_ENABLE != 0
+    }
+
+    // LY already reads 0 for most of the last line
+    fn ly_register(&self) -> u8 {
+        if self.ly == Gpu::LINES_PER_FRAME - 1 && self.dots >= 4 {
+            0
+        } else {
+            self.ly
+        }
+    }
+
+    fn lyc_equal(&self) -> u8 {
+        if self.lcd_enabled() && self.ly_register() == self.lyc {
+            Gpu::LYC_EQUAL
+        } else {
+            0
+        }
+    }
+
+    // returns the mask of interrupts requested during these cycles
+    pub fn step(&mut self, cycles: u32) -> u8 {
+        if !self.lcd_enabled() {
+            // a disabled LCD shows a blank screen but frames keep coming at the same pace
+            self.dots += cycles;
+            if self.dots >= Gpu::FRAME_DOTS {
+                self.dots -= Gpu::FRAME_DOTS;
+                self.framebuffer.fill(0);
+                self.frame_ready = true;
+            }
+            return 0;
+        }
+
+        let mut interrupts = 0;
+        for _ in 0..cycles {
+            interrupts |= self.tick();
+        }
+        interrupts
+    }
+
+    fn tick(&mut self) -> u8 {
+        let mut interrupts = 0;
+
+        self.dots += 1;
+        if self.dots == Gpu::DOTS_PER_LINE {
+            self.dots = 0;
+            self.ly += 1;
+            if self.ly == Gpu::LINES_PER_FRAME {
+                self.ly = 0;
+                self.start_frame();
+            }
+        }
+
+        // mode 3 lasts as long as the pixel FIFO needs to output the whole line
+        let mode = match self.mode {
+            _ if usize::from(self.ly) >= SCREEN_HEIGHT => Mode::VBlank,
+            _ if self.dots < Gpu::OAM_SCAN_DOTS => Mode::OamScan,
+            Mode::OamScan => Mode::Drawing,
+            Mode::Drawing if self.fifo.line_done() => Mode::HBlank,
+            mode => mode,
+        };
+        if mode != self.mode {
+            match mode {
+                Mode::Drawing => self.start_drawing(),
+                Mode::HBlank => {
+                    if self.fifo.window_active() {
+                        self.window_line += 1;
+                    }
+                }
+                Mode::VBlank => {
+                    interrupts |= Interrupt::VBlank.mask();
+                    self.frame_ready = true;
+                }
+                Mode::OamScan => (),
+            }
+            self.mode = mode;
+        }
+        if self.mode == Mode::Drawing {
+            self.draw_dot();
+        }
+
+        // the four STAT sources share a single line, so an interrupt is only requested on its rising edge
+        let stat_line = self.stat & Gpu::LYC_INTERRUPT != 0 && self.lyc_equal() != 0
+            || match self.mode {
+                Mode::HBlank => self.stat & Gpu::HBLANK_INTERRUPT != 0,
+                // the OAM source also fires at the start of VBlank
+                Mode::VBlank => {
+                    self.stat & Gpu::VBLANK_INTERRUPT != 0
+                        || self.stat & Gpu::OAM_INTERRUPT != 0 && usize::from(self.ly) == SCREEN_HEIGHT && self.dots == 0
+                }
+                Mode::OamScan => self.stat & Gpu::OAM_INTERRUPT != 0,
+                Mode::Drawing => false,
+            };
+        if stat_line && !self.stat_line {
+            interrupts |= Interrupt::LcdStat.mask();
+        }
+        self.stat_line = stat_line;
+        interrupts
+    }
+
+    fn start_frame(&mut self) {
+        self.window_line = 0;
+        self.window_y_triggered = false;
+        self.window_full_line = false;
+    }
+
+    fn sprite_height(&self) -> u8 {
+        if self.lcdc & Gpu::OBJ_SIZE != 0 {
+            16
+        } else {
+            8
+        }
+    }
+
+    // the first 10 sprites in OAM order overlapping the line are drawn, no matter their X position
+    fn scan_oam(&self, line: u8) -> Vec<Sprite> {
+        let height = self.sprite_height();
+        (0..Gpu::SPRITE_COUNT)
+            .map(|oam_index| Sprite::from_oam(&self.oam, oam_index))
+            .filter(|sprite| sprite.is_on_line(line, height))
+            .take(Sprite::MAX_PER_LINE)
+            .collect()
+    }
+
+    // 0x8000 addressing uses unsigned indexes, 0x8800 signed ones relative to 0x9000
+    fn tile_address(&self, tile_index: u8) -> u16 {
+        if self.lcdc & Gpu::TILE_DATA != 0 {
+            0x8000 + u16::from(tile_index) * 16
+        } else {
+            0x9000u16.wrapping_add((tile_index as i8 as i16 * 16) as u16)
+        }
+    }
+
+    fn shade(palette: u8, color: u8) -> u8 {
+        (palette >> (color * 2)) & 0b11
+    }
+
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    // reports a completed frame once, as soon as the last visible line is drawn
+    pub fn take_frame_ready(&mut self) -> bool {
+        let frame_ready = self.frame_ready;
+        self.frame_ready = false;
+        frame_ready
+    }
+}
+
+impl Default for Gpu {
+    fn default() -> Gpu {
+        Gpu::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_tile(gpu: &mut Gpu, address: u16, rows: &[u8; 16]) {
+        for (offset, value) in rows.iter().enumerate() {
+            gpu.write_byte(address + offset as u16, *value);
+        }
+    }
+
+    fn pixel(gpu: &Gpu, x: usize, y: usize) -> u8 {
+        gpu.framebuffer()[y * SCREEN_WIDTH + x]
+    }
+
+    #[test]
+    fn gpu_frame_timing_test() {
+        let mut gpu = Gpu::new();
+        assert_eq!(gpu.read_byte(0xFF41), 0x80);
+        gpu.write_byte(0xFF40, 0x91);
+        gpu.write_byte(0xFF44, 0x12);
+        assert_eq!(gpu.read_byte(0xFF44), 0);
+
+        // each visible line goes through OAM scan, drawing and HBlank
+        assert_eq!(gpu.step(79), 0);
+        assert_eq!(gpu.read_byte(0xFF41) & 0b11, 2);
+        gpu.step(1);
+        assert_eq!(gpu.read_byte(0xFF41) & 0b11, 3);
+        gpu.step(171);
+        assert_eq!(gpu.read_byte(0xFF41) & 0b11, 3);
+        gpu.step(1);
+        assert_eq!(gpu.read_byte(0xFF41) & 0b11, 0);
+        gpu.step(203);
+        assert_eq!((gpu.read_byte(0xFF44), gpu.read_byte(0xFF41) & 0b11), (0, 0));
+        gpu.step(1);
+        assert_eq!((gpu.read_byte(0xFF44), gpu.read_byte(0xFF41) & 0b11), (1, 2));
+
+        gpu.step(Gpu::DOTS_PER_LINE * 143 - 1);
+        assert_eq!(gpu.read_byte(0xFF44), 143);
+        assert!(!gpu.take_frame_ready());
+        assert_eq!(gpu.step(1), Interrupt::VBlank.mask());
+        assert_eq!((gpu.read_byte(0xFF44), gpu.read_byte(0xFF41) & 0b11), (144, 1));
+        assert!(gpu.take_frame_ready());
+        assert!(!gpu.take_frame_ready());
+
+        // LY wraps to 0 early on the last line
+        gpu.step(Gpu::DOTS_PER_LINE * 9);
+        assert_eq!(gpu.read_byte(0xFF44), 153);
+        gpu.step(4);
+        assert_eq!((gpu.read_byte(0xFF44), gpu.read_byte(0xFF41) & 0b11), (0, 1));
+        gpu.step(Gpu::DOTS_PER_LINE - 4);
+        assert_eq!((gpu.read_byte(0xFF44), gpu.read_byte(0xFF41) & 0b11), (0, 2));
+
+        // frames keep coming with the LCD off
+        gpu.write_byte(0xFF40, 0x00);
+        assert_eq!(gpu.read_byte(0xFF41) & 0b11, 0);
+        assert_eq!(gpu.step(Gpu::FRAME_DOTS), 0);
+        assert!(gpu.take_frame_ready());
+        assert_eq!(gpu.read_byte(0xFF44), 0);
+    }
+
+    #[test]
+    fn gpu_stat_interrupts_test() {
+        let mut gpu = Gpu::new();
+        gpu.write_byte(0xFF40, 0x91);
+        gpu.write_byte(0xFF45, 2);
+        gpu.write_byte(0xFF41, 0xFF);
+        assert_eq!(gpu.read_byte(0xFF41), 0xFA);
+
+        // OAM scan of line 0, then HBlank
+        assert_eq!(gpu.step(1), Interrupt::LcdStat.mask());
+        assert_eq!(gpu.step(250), 0);
+        assert_eq!(gpu.step(1), Interrupt::LcdStat.mask());
+
+        // HBlank into OAM scan keeps the line high, no new interrupt
+        gpu.step(203);
+        assert_eq!(gpu.step(1), 0);
+        assert_eq!(gpu.read_byte(0xFF44), 1);
+
+        // LYC on line 2 overlaps with OAM scan and HBlank, keeping the line high through drawing
+        assert_eq!(gpu.step(Gpu::DOTS_PER_LINE), Interrupt::LcdStat.mask());
+        assert_eq!(gpu.read_byte(0xFF41), 0xFE);
+        assert_eq!(gpu.step(Gpu::DOTS_PER_LINE), 0);
+        assert_eq!(gpu.read_byte(0xFF41), 0xFA);
+
+        gpu.write_byte(0xFF41, Gpu::LYC_INTERRUPT);
+        gpu.write_byte(0xFF45, 4);
+        assert_eq!(gpu.step(Gpu::DOTS_PER_LINE), Interrupt::LcdStat.mask());
+        assert_eq!(gpu.step(Gpu::DOTS_PER_LINE), 0);
+
+        // VBlank requests both its own interrupt and the STAT one for the OAM source
+        gpu.write_byte(0xFF41, Gpu::OAM_INTERRUPT);
+        gpu.step(Gpu::DOTS_PER_LINE * 139 - 1);
+        assert_eq!(gpu.read_byte(0xFF44), 143);
+        assert_eq!(gpu.step(1), Interrupt::VBlank.mask() | Interrupt::LcdStat.mask());
+        assert_eq!(gpu.step(Gpu::DOTS_PER_LINE * 10 - 1), 0);
+        assert_eq!(gpu.step(1), Interrupt::LcdStat.mask());
+    }
+
+    #[test]
+    fn gpu_background_test() {
+        let mut gpu = Gpu::new();
+        // tile 1 has colors 0-3 in its first row, tile 2 is solid color 3
+        write_tile(&mut gpu, 0x8010, &[0b0101_0101, 0b0011_0011, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        write_tile(&mut gpu, 0x8020, &[0xFF; 16]);
+        gpu.write_byte(0x9800, 0x01);
+        gpu.write_byte(0x9801, 0x02);
+        gpu.write_byte(0xFF47, 0b1110_0100);
+        gpu.write_byte(0xFF40, 0x91);
+        gpu.step(Gpu::FRAME_DOTS);
+
+        assert_eq!(
+            gpu.framebuffer()[0..8],
+            [0, 1, 2, 3, 0, 1, 2, 3]
+        );
+        assert_eq!(pixel(&gpu, 8, 0), 3);
+        assert_eq!(pixel(&gpu, 8, 7), 3);
+        assert_eq!(pixel(&gpu, 0, 1), 0);
+        assert_eq!(pixel(&gpu, 16, 0), 0);
+
+        // palette remaps colors
+        gpu.write_byte(0xFF47, 0b0001_1011);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(gpu.framebuffer()[0..4], [3, 2, 1, 0]);
+
+        // disabled background is white
+        gpu.write_byte(0xFF40, 0x90);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 8, 0), 0);
+    }
+
+    #[test]
+    fn gpu_background_scroll_test() {
+        let mut gpu = Gpu::new();
+        write_tile(&mut gpu, 0x8010, &[0xFF; 16]);
+        // the bottom right tile of the map wraps around to the top left corner of the screen
+        gpu.write_byte(0x9BFF, 0x01);
+        gpu.write_byte(0xFF47, 0b1110_0100);
+        gpu.write_byte(0xFF42, 0xFC);
+        gpu.write_byte(0xFF43, 0xFE);
+        gpu.write_byte(0xFF40, 0x91);
+        gpu.step(Gpu::FRAME_DOTS);
+
+        assert_eq!(pixel(&gpu, 0, 0), 3);
+        assert_eq!(pixel(&gpu, 1, 3), 3);
+        assert_eq!(pixel(&gpu, 2, 0), 0);
+        assert_eq!(pixel(&gpu, 0, 4), 0);
+    }
+
+    #[test]
+    fn gpu_window_test() {
+        let mut gpu = Gpu::new();
+        // window map at 0x9C00 points at tile 1 with color 1 in columns 0-3 and color 2 in columns 4-7
+        write_tile(&mut gpu, 0x8010, &[0xF0, 0x0F, 0xF0, 0x0F, 0xF0, 0x0F, 0xF0, 0x0F, 0xF0, 0x0F, 0xF0, 0x0F, 0xF0, 0x0F, 0xF0, 0x0F]);
+        for offset in 0..0x400 {
+            gpu.write_byte(0x9C00 + offset, 0x01);
+        }
+        gpu.write_byte(0xFF47, 0b1110_0100);
+        gpu.write_byte(0xFF4A, 10);
+        gpu.write_byte(0xFF4B, 27);
+        gpu.write_byte(0xFF40, 0xF1);
+        gpu.step(Gpu::FRAME_DOTS);
+
+        assert_eq!(pixel(&gpu, 19, 10), 0);
+        assert_eq!(pixel(&gpu, 20, 10), 1);
+        assert_eq!(pixel(&gpu, 24, 10), 2);
+        assert_eq!(pixel(&gpu, 159, 143), 1);
+        assert_eq!(pixel(&gpu, 20, 9), 0);
+
+        // window disabled by LCDC bit 5 or by the background enable bit
+        gpu.write_byte(0xFF40, 0xD1);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 20, 10), 0);
+        gpu.write_byte(0xFF40, 0xF0);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 20, 10), 0);
+    }
+
+    #[test]
+    fn gpu_window_line_counter_test() {
+        let mut gpu = Gpu::new();
+        // window row 0 is color 3, every other row color 0
+        write_tile(&mut gpu, 0x8010, &[0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        gpu.write_byte(0x9C00, 0x01);
+        gpu.write_byte(0xFF47, 0b1110_0100);
+        gpu.write_byte(0xFF4B, 7);
+        gpu.write_byte(0xFF40, 0xF1);
+
+        // the window is hidden on lines 1-4, the counter holds and line 5 draws window row 1
+        gpu.step(Gpu::DOTS_PER_LINE);
+        gpu.write_byte(0xFF40, 0xD1);
+        gpu.step(Gpu::DOTS_PER_LINE * 4);
+        gpu.write_byte(0xFF40, 0xF1);
+        gpu.step(Gpu::DOTS_PER_LINE);
+        assert_eq!(pixel(&gpu, 0, 0), 3);
+        assert_eq!(pixel(&gpu, 0, 5), 0);
+
+        // same with WX moved off screen
+        gpu.step(Gpu::FRAME_DOTS - Gpu::DOTS_PER_LINE * 6);
+        gpu.step(Gpu::DOTS_PER_LINE);
+        gpu.write_byte(0xFF4B, 200);
+        gpu.step(Gpu::DOTS_PER_LINE * 4);
+        gpu.write_byte(0xFF4B, 7);
+        gpu.step(Gpu::DOTS_PER_LINE);
+        assert_eq!(pixel(&gpu, 0, 4), 0);
+        assert_eq!(pixel(&gpu, 0, 5), 0);
+        assert_eq!(pixel(&gpu, 0, 0), 3);
+    }
+
+    #[test]
+    fn gpu_window_edge_cases_test() {
+        let mut gpu = Gpu::new();
+        // window tile 1 has a single color 3 pixel at column 7 of its first row
+        write_tile(&mut gpu, 0x8010, &[0x01, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        gpu.write_byte(0x9C00, 0x01);
+        gpu.write_byte(0xFF47, 0b1110_0100);
+        gpu.write_byte(0xFF40, 0xF1);
+
+        // WX=0 hides 7 window pixels plus the background fine scroll
+        gpu.write_byte(0xFF4B, 0);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 0, 0), 3);
+        gpu.write_byte(0xFF43, 0x03);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 0, 0), 0);
+
+        // WX=166 skips the first window line and covers the whole next one
+        gpu.write_byte(0xFF4B, 166);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 159, 0), 0);
+        assert_eq!(pixel(&gpu, 7, 1), 3);
+        assert_eq!(pixel(&gpu, 7, 2), 0);
+    }
+
+    fn write_sprite(gpu: &mut Gpu, oam_index: u16, y: u8, x: u8, tile: u8, attributes: u8) {
+        let address = 0xFE00 + oam_index * 4;
+        gpu.write_byte(address, y);
+        gpu.write_byte(address + 1, x);
+        gpu.write_byte(address + 2, tile);
+        gpu.write_byte(address + 3, attributes);
+    }
+
+    fn sprite_gpu() -> Gpu {
+        let mut gpu = Gpu::new();
+        // tile 1 is solid color 1, tile 2 solid color 2, tile 3 has color 3 in the top left pixel only
+        write_tile(&mut gpu, 0x8010, &[0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00]);
+        write_tile(&mut gpu, 0x8020, &[0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF]);
+        write_tile(&mut gpu, 0x8030, &[0x80, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        gpu.write_byte(0xFF47, 0b1110_0100);
+        gpu.write_byte(0xFF48, 0b1110_0100);
+        gpu.write_byte(0xFF49, 0b0001_1011);
+        gpu
+    }
+
+    #[test]
+    fn gpu_sprite_test() {
+        let mut gpu = sprite_gpu();
+        write_sprite(&mut gpu, 0, 16, 48, 0x03, 0x00);
+        write_sprite(&mut gpu, 1, 26, 18, 0x03, 0b0110_0000);
+        write_sprite(&mut gpu, 2, 36, 28, 0x02, 0b0001_0000);
+        // partially off screen to the top left
+        write_sprite(&mut gpu, 3, 0x0F, 0x07, 0x02, 0x00);
+        gpu.write_byte(0xFF40, 0x93);
+        gpu.step(Gpu::FRAME_DOTS);
+
+        assert_eq!(pixel(&gpu, 40, 0), 3);
+        assert_eq!(pixel(&gpu, 41, 0), 0);
+        assert_eq!(pixel(&gpu, 0, 0), 2);
+        assert_eq!(pixel(&gpu, 6, 6), 2);
+        assert_eq!(pixel(&gpu, 7, 7), 0);
+        // flipped on both axes
+        assert_eq!(pixel(&gpu, 17, 17), 3);
+        assert_eq!(pixel(&gpu, 10, 10), 0);
+        // OBP1
+        assert_eq!(pixel(&gpu, 20, 20), 1);
+        assert_eq!(pixel(&gpu, 27, 27), 1);
+        assert_eq!(pixel(&gpu, 28, 28), 0);
+
+        // sprites disabled
+        gpu.write_byte(0xFF40, 0x91);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 40, 0), 0);
+    }
+
+    #[test]
+    fn gpu_tall_sprite_test() {
+        let mut gpu = sprite_gpu();
+        // tile 3 is used as 2, the lower half comes from tile 3
+        write_sprite(&mut gpu, 0, 16, 8, 0x03, 0x00);
+        write_sprite(&mut gpu, 1, 16, 16, 0x02, 0b0100_0000);
+        gpu.write_byte(0xFF40, 0x97);
+        gpu.step(Gpu::FRAME_DOTS);
+
+        assert_eq!(pixel(&gpu, 0, 0), 2);
+        assert_eq!(pixel(&gpu, 0, 7), 2);
+        assert_eq!(pixel(&gpu, 0, 8), 3);
+        assert_eq!(pixel(&gpu, 1, 8), 0);
+        assert_eq!(pixel(&gpu, 0, 16), 0);
+        // the flip swaps both halves
+        assert_eq!(pixel(&gpu, 8, 15), 2);
+        assert_eq!(pixel(&gpu, 8, 7), 3);
+        assert_eq!(pixel(&gpu, 8, 0), 0);
+    }
+
+    #[test]
+    fn gpu_sprite_size_change_test() {
+        let mut gpu = sprite_gpu();
+        write_sprite(&mut gpu, 0, 16, 8, 0x03, 0x00);
+        write_sprite(&mut gpu, 1, 16, 16, 0x02, 0b0100_0000);
+        gpu.write_byte(0xFF40, 0x97);
+        gpu.step(8 * 456 + 80);
+
+        // both sprites were picked for line 8 as 8x16, then fetched as 8x8 from row 0 of their tile
+        gpu.write_byte(0xFF40, 0x93);
+        gpu.step(456 - 80);
+        assert_eq!(pixel(&gpu, 0, 8), 3);
+        assert_eq!(pixel(&gpu, 1, 8), 0);
+        assert_eq!(pixel(&gpu, 8, 8), 2);
+    }
+
+    #[test]
+    fn gpu_sprite_priority_test() {
+        let mut gpu = sprite_gpu();
+        // lower X wins over the OAM order, equal X falls back to it
+        write_sprite(&mut gpu, 0, 16, 12, 0x02, 0x00);
+        write_sprite(&mut gpu, 1, 16, 10, 0x01, 0x00);
+        write_sprite(&mut gpu, 2, 24, 40, 0x02, 0x00);
+        write_sprite(&mut gpu, 3, 24, 40, 0x01, 0x00);
+        // transparent pixels of the winning sprite show the one below
+        write_sprite(&mut gpu, 4, 32, 60, 0x03, 0x00);
+        write_sprite(&mut gpu, 5, 32, 61, 0x02, 0x00);
+        gpu.write_byte(0xFF40, 0x93);
+        gpu.step(Gpu::FRAME_DOTS);
+
+        assert_eq!(pixel(&gpu, 2, 0), 1);
+        assert_eq!(pixel(&gpu, 9, 0), 1);
+        assert_eq!(pixel(&gpu, 10, 0), 2);
+        assert_eq!(pixel(&gpu, 32, 8), 2);
+        assert_eq!(pixel(&gpu, 52, 16), 3);
+        assert_eq!(pixel(&gpu, 53, 16), 2);
+    }
+
+    #[test]
+    fn gpu_sprite_background_priority_test() {
+        let mut gpu = sprite_gpu();
+        // background is color 1 on the left half of the first tile row, color 0 elsewhere
+        write_tile(&mut gpu, 0x8040, &[0xF0, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        gpu.write_byte(0x9800, 0x04);
+        // sprite behind the background on top of a regular sprite
+        write_sprite(&mut gpu, 0, 16, 8, 0x02, 0b1000_0000);
+        write_sprite(&mut gpu, 1, 16, 9, 0x02, 0x00);
+        gpu.write_byte(0xFF40, 0x93);
+        gpu.step(Gpu::FRAME_DOTS);
+
+        assert_eq!(pixel(&gpu, 0, 0), 1);
+        assert_eq!(pixel(&gpu, 3, 0), 1);
+        assert_eq!(pixel(&gpu, 4, 0), 2);
+        assert_eq!(pixel(&gpu, 0, 1), 2);
+
+        // with the background disabled every sprite is on top
+        gpu.write_byte(0xFF40, 0x92);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 0, 0), 2);
+    }
+
+    #[test]
+    fn gpu_sprites_per_line_test() {
+        let mut gpu = sprite_gpu();
+        // sprites off screen horizontally still count toward the limit of 10
+        write_sprite(&mut gpu, 0, 16, 0, 0x01, 0x00);
+        for oam_index in 1..12 {
+            write_sprite(&mut gpu, oam_index, 16, oam_index as u8 * 8, 0x01, 0x00);
+        }
+        gpu.write_byte(0xFF40, 0x93);
+        gpu.step(Gpu::FRAME_DOTS);
+
+        for x in 0..72 {
+            assert_eq!(pixel(&gpu, x, 0), 1);
+        }
+        assert_eq!(pixel(&gpu, 72, 0), 0);
+        assert_eq!(pixel(&gpu, 80, 0), 0);
+    }
+
+    // steps through the OAM scan of the first line and counts the dots spent in mode 3
+    fn drawing_dots(gpu: &mut Gpu, lcdc: u8) -> u32 {
+        gpu.write_byte(0xFF40, 0x00);
+        gpu.write_byte(0xFF40, lcdc);
+        gpu.step(80);
+        let mut dots = 0;
+        while gpu.read_byte(0xFF41) & 0b11 == 3 {
+            gpu.step(1);
+            dots += 1;
+        }
+        dots
+    }
+
+    #[test]
+    fn gpu_drawing_length_test() {
+        let mut gpu = sprite_gpu();
+        assert_eq!(drawing_dots(&mut gpu, 0x93), 172);
+
+        // fine scroll pixels are fetched and thrown away
+        gpu.write_byte(0xFF43, 0x03);
+        assert_eq!(drawing_dots(&mut gpu, 0x93), 175);
+        gpu.write_byte(0xFF43, 0x08);
+        assert_eq!(drawing_dots(&mut gpu, 0x93), 172);
+
+        // the window restarts the background fetcher
+        gpu.write_byte(0xFF43, 0x00);
+        gpu.write_byte(0xFF4B, 87);
+        assert_eq!(drawing_dots(&mut gpu, 0xB3), 178);
+    }
+
+    #[test]
+    fn gpu_sprite_stall_test() {
+        let mut gpu = sprite_gpu();
+        // the wait for the background fetcher depends on where the sprite lands within a tile
+        write_sprite(&mut gpu, 0, 16, 8, 0x01, 0x00);
+        assert_eq!(drawing_dots(&mut gpu, 0x93), 183);
+        write_sprite(&mut gpu, 0, 16, 13, 0x01, 0x00);
+        assert_eq!(drawing_dots(&mut gpu, 0x93), 178);
+        write_sprite(&mut gpu, 0, 16, 0, 0x01, 0x00);
+        assert_eq!(drawing_dots(&mut gpu, 0x93), 183);
+
+        // sprites are not fetched while they are disabled
+        gpu.write_byte(0xFF40, 0x00);
+        gpu.write_byte(0xFF40, 0x91);
+        gpu.step(80);
+        gpu.step(172);
+        assert_eq!(gpu.read_byte(0xFF41) & 0b11, 0);
+
+        // every sprite on the line adds its own stall
+        for oam_index in 1..10 {
+            write_sprite(&mut gpu, oam_index, 16, 8 + oam_index as u8 * 16, 0x01, 0x00);
+        }
+        assert!(drawing_dots(&mut gpu, 0x93) > 172 + 10 * 6);
+    }
+
+    #[test]
+    fn gpu_mid_line_register_writes_test() {
+        let mut gpu = sprite_gpu();
+        for offset in 0..0x400 {
+            gpu.write_byte(0x9800 + offset, 0x01);
+        }
+        gpu.write_byte(0xFF40, 0x91);
+
+        // the first pixel is output 12 dots into mode 3, one pixel per dot after that
+        gpu.step(80 + 12 + 50);
+        gpu.write_byte(0xFF47, 0b1110_1100);
+        gpu.step(Gpu::DOTS_PER_LINE - 80 - 12 - 50);
+        assert_eq!(pixel(&gpu, 50, 0), 1);
+        assert_eq!(pixel(&gpu, 51, 0), 3);
+        assert_eq!(pixel(&gpu, 159, 0), 3);
+
+        // coarse scroll is read by the fetcher at each tile
+        for offset in 0..0x400 {
+            gpu.write_byte(0x9800 + offset, if offset % 32 < 16 { 0x01 } else { 0x02 });
+        }
+        gpu.write_byte(0xFF47, 0b1110_0100);
+        gpu.step(80 + 12 + 64);
+        gpu.write_byte(0xFF43, 0x40);
+        gpu.step(Gpu::DOTS_PER_LINE - 80 - 12 - 64);
+        // the tile under pixel 64 is already in the FIFO, the next one is fetched 8 tiles further
+        assert_eq!(pixel(&gpu, 71, 1), 1);
+        assert_eq!(pixel(&gpu, 72, 1), 2);
+        assert_eq!(pixel(&gpu, 159, 1), 2);
+    }
+
+    #[test]
+    fn gpu_tile_addressing_test() {
+        let mut gpu = Gpu::new();
+        // tile 0x80 is at 0x8800 in both modes, tile 0x00 at 0x9000 in signed mode
+        write_tile(&mut gpu, 0x8800, &[0xFF, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        write_tile(&mut gpu, 0x9000, &[0x00, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        gpu.write_byte(0x9C00, 0x80);
+        gpu.write_byte(0x9C01, 0x00);
+        gpu.write_byte(0xFF47, 0b1110_0100);
+
+        gpu.write_byte(0xFF40, 0x89);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 0, 0), 1);
+        assert_eq!(pixel(&gpu, 8, 0), 2);
+
+        gpu.write_byte(0xFF40, 0x99);
+        gpu.step(Gpu::FRAME_DOTS);
+        assert_eq!(pixel(&gpu, 0, 0), 1);
+        assert_eq!(pixel(&gpu, 8, 0), 0);
+    }
+}
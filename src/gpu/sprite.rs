@@ -0,0 +1,54 @@
+use crate::mmu::Memory;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    // screen position offset by (8, 16) so sprites can scroll in from the top left edge
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: u8,
+    pub oam_index: u8,
+}
+
+impl Sprite {
+    pub const SIZE: u16 = 4;
+    pub const MAX_PER_LINE: usize = 10;
+
+    const BG_PRIORITY: u8 = 0b1000_0000;
+    const Y_FLIP: u8 = 0b0100_0000;
+    const X_FLIP: u8 = 0b0010_0000;
+    const PALETTE: u8 = 0b0001_0000;
+
+    pub fn from_oam(oam: &Memory, oam_index: u8) -> Sprite {
+        let offset = u16::from(oam_index) * Sprite::SIZE;
+        Sprite {
+            y: oam.read_byte(offset),
+            x: oam.read_byte(offset + 1),
+            tile: oam.read_byte(offset + 2),
+            attributes: oam.read_byte(offset + 3),
+            oam_index,
+        }
+    }
+
+    pub fn is_on_line(&self, line: u8, height: u8) -> bool {
+        let line = u16::from(line) + 16;
+        let y = u16::from(self.y);
+        line >= y && line < y + u16::from(height)
+    }
+
+    pub fn behind_background(&self) -> bool {
+        self.attributes & Sprite::BG_PRIORITY != 0
+    }
+
+    pub fn x_flip(&self) -> bool {
+        self.attributes & Sprite::X_FLIP != 0
+    }
+
+    pub fn y_flip(&self) -> bool {
+        self.attributes & Sprite::Y_FLIP != 0
+    }
+
+    pub fn uses_obp1(&self) -> bool {
+        self.attributes & Sprite::PALETTE != 0
+    }
+}
@@ -0,0 +1,137 @@
+use super::mmu::Interrupt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    // the d-pad sits in the low nibble and the buttons in the high one, in P1 line order
+    pub fn mask(self) -> u8 {
+        match self {
+            Button::Right => 0b0000_0001,
+            Button::Left => 0b0000_0010,
+            Button::Up => 0b0000_0100,
+            Button::Down => 0b0000_1000,
+            Button::A => 0b0001_0000,
+            Button::B => 0b0010_0000,
+            Button::Select => 0b0100_0000,
+            Button::Start => 0b1000_0000,
+        }
+    }
+}
+
+pub struct Joypad {
+    // P14 and P15, a cleared bit selects the d-pad or the buttons
+    select: u8,
+    pressed: u8,
+}
+
+impl Joypad {
+    const SELECT_DPAD: u8 = 0b0001_0000;
+    const SELECT_BUTTONS: u8 = 0b0010_0000;
+
+    pub fn new() -> Joypad {
+        Joypad {
+            select: Joypad::SELECT_DPAD | Joypad::SELECT_BUTTONS,
+            pressed: 0x00,
+        }
+    }
+
+    pub fn skip_boot(&mut self) {
+        self.select = 0x00;
+    }
+
+    pub fn read_register(&self) -> u8 {
+        0b1100_0000 | self.select | self.lines()
+    }
+
+    pub fn pressed(&self) -> u8 {
+        self.pressed
+    }
+
+    // both return the interrupts requested by input lines going low
+    pub fn select(&mut self, value: u8) -> u8 {
+        let lines = self.lines();
+        self.select = value & (Joypad::SELECT_DPAD | Joypad::SELECT_BUTTONS);
+        Joypad::falling_edge_interrupt(lines, self.lines())
+    }
+
+    pub fn set_pressed(&mut self, pressed: u8) -> u8 {
+        let lines = self.lines();
+        self.pressed = pressed;
+        Joypad::falling_edge_interrupt(lines, self.lines())
+    }
+
+    // P10 - P13 are pulled low by any pressed key of a selected group
+    fn lines(&self) -> u8 {
+        let mut pressed = 0;
+        if self.select & Joypad::SELECT_DPAD == 0 {
+            pressed |= self.pressed & 0x0F;
+        }
+        if self.select & Joypad::SELECT_BUTTONS == 0 {
+            pressed |= self.pressed >> 4;
+        }
+        !pressed & 0x0F
+    }
+
+    fn falling_edge_interrupt(old_lines: u8, new_lines: u8) -> u8 {
+        if old_lines & !new_lines != 0 {
+            Interrupt::Joypad.mask()
+        } else {
+            0
+        }
+    }
+}
+
+impl Default for Joypad {
+    fn default() -> Joypad {
+        Joypad::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joypad_register_test() {
+        let mut joypad = Joypad::new();
+        assert_eq!(joypad.read_register(), 0xFF);
+        joypad.set_pressed(Button::Down.mask() | Button::A.mask() | Button::Start.mask());
+        assert_eq!(joypad.read_register(), 0xFF);
+
+        joypad.select(0x20);
+        assert_eq!(joypad.read_register(), 0xE7);
+        joypad.select(0x10);
+        assert_eq!(joypad.read_register(), 0xD6);
+        joypad.select(0x00);
+        assert_eq!(joypad.read_register(), 0xC6);
+
+        // the input lines are read-only
+        joypad.select(0xFF);
+        assert_eq!(joypad.read_register(), 0xFF);
+        joypad.skip_boot();
+        joypad.set_pressed(0x00);
+        assert_eq!(joypad.read_register(), 0xCF);
+    }
+
+    #[test]
+    fn joypad_interrupt_test() {
+        let mut joypad = Joypad::new();
+        assert_eq!(joypad.set_pressed(Button::B.mask()), 0);
+        assert_eq!(joypad.select(0x10), Interrupt::Joypad.mask());
+        assert_eq!(joypad.set_pressed(Button::B.mask() | Button::Left.mask()), 0);
+        assert_eq!(joypad.set_pressed(Button::B.mask() | Button::Select.mask()), Interrupt::Joypad.mask());
+        assert_eq!(joypad.set_pressed(Button::Select.mask()), 0);
+        assert_eq!(joypad.set_pressed(0x00), 0);
+        assert_eq!(joypad.select(0x30), 0);
+    }
+}
@@ -0,0 +1,120 @@
+pub mod apu;
+pub mod cartridge;
+pub mod cpu;
+pub mod gpu;
+pub mod joypad;
+pub mod mmu;
+pub mod serial;
+pub mod timer;
+use self::{
+    cartridge::Cartridge,
+    cpu::{Cpu, CpuState},
+    joypad::Button,
+    mmu::{BootRom, Mmu},
+    serial::SerialDevice,
+};
+use std::io;
+
+pub struct Gameboy {
+    cpu: Cpu,
+    mmu: Mmu
+}
+
+impl Gameboy {
+    // starts straight at the cartridge entry point with the state the boot ROM would leave behind
+    pub fn new(cartridge: Cartridge) -> Gameboy {
+        let mut cpu = Cpu::new();
+        let mut mmu = Mmu::new(cartridge);
+        cpu.skip_boot();
+        mmu.skip_boot();
+        Gameboy { cpu, mmu }
+    }
+
+    pub fn with_boot_rom(cartridge: Cartridge, boot_rom: BootRom) -> Gameboy {
+        Gameboy {
+            cpu: Cpu::new(),
+            mmu: Mmu::with_boot_rom(cartridge, boot_rom),
+        }
+    }
+
+    // lets frontends drive a force feedback device while a rumble cartridge runs its motor
+    pub fn rumble(&self) -> bool {
+        self.mmu.cartridge().rumble()
+    }
+
+    // writes battery-backed RAM to disk, should be called before the frontend exits
+    pub fn flush_save(&mut self) -> io::Result<()> {
+        self.mmu.cartridge_mut().flush_save()
+    }
+
+    // the last error of the once per second save flush, the flush is retried until it succeeds
+    pub fn take_save_error(&mut self) -> Option<io::Error> {
+        self.mmu.cartridge_mut().take_save_error()
+    }
+
+    pub fn cpu_state(&self) -> CpuState {
+        self.cpu.state()
+    }
+
+    // 160x144 shades from 0 (white) to 3 (black), complete after each run_frame
+    pub fn framebuffer(&self) -> &[u8] {
+        self.mmu.gpu().framebuffer()
+    }
+
+    pub fn press(&mut self, button: Button) {
+        let buttons = self.mmu.buttons() | button.mask();
+        self.mmu.set_buttons(buttons);
+    }
+
+    pub fn release(&mut self, button: Button) {
+        let buttons = self.mmu.buttons() & !button.mask();
+        self.mmu.set_buttons(buttons);
+    }
+
+    // replaces the whole input state, for frontends that poll every button once per frame
+    pub fn set_buttons(&mut self, buttons: &[Button]) {
+        let buttons = buttons.iter().fold(0, |pressed, button| pressed | button.mask());
+        self.mmu.set_buttons(buttons);
+    }
+
+    // plugs something into the link port, replacing whatever was connected before
+    pub fn connect_serial(&mut self, device: Box<dyn SerialDevice>) {
+        self.mmu.serial_mut().connect(device);
+    }
+
+    pub fn disconnect_serial(&mut self) -> Option<Box<dyn SerialDevice>> {
+        self.mmu.serial_mut().disconnect()
+    }
+
+    // the host output rate in Hz, 48000 unless changed
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.mmu.apu_mut().set_sample_rate(sample_rate);
+    }
+
+    // interleaved left and right samples generated since the last call
+    pub fn take_audio_samples(&mut self) -> Vec<i16> {
+        self.mmu.apu_mut().take_samples()
+    }
+
+    // pull API for audio callbacks, out takes interleaved left and right samples so its length must
+    // be even, pads with silence when emulation falls behind and returns how many samples it had
+    pub fn fill_audio(&mut self, out: &mut [i16]) -> usize {
+        self.mmu.apu_mut().fill_audio(out)
+    }
+
+    // returns false once the CPU locks up on an illegal opcode
+    pub fn run_frame(&mut self) -> bool {
+        while let CpuState::Running | CpuState::Halted | CpuState::Stopped = self.cpu.state() {
+            self.cpu.step(&mut self.mmu);
+            if self.mmu.gpu_mut().take_frame_ready() {
+                return true;
+            }
+        }
+        false
+    }
+
+    // runs until the CPU locks up on an illegal opcode
+    pub fn run(&mut self) {
+        while self.run_frame() {}
+    }
+}
@@ -1,32 +1,65 @@
-mod cpu;
-mod gpu;
-mod mmu;
-use self::{cpu::Cpu, gpu::Gpu, mmu::Mmu};
+use gbe::{cartridge::Cartridge, mmu::BootRom, Gameboy};
+use std::{env, process};
 
-struct Gameboy {
-    cpu: Cpu,
-    gpu: Gpu,
-    mmu: Mmu
+fn usage() -> ! {
+    eprintln!("usage: gbe [--sync-rtc] [--boot-rom <boot rom file>] <rom file>");
+    process::exit(1);
 }
 
-impl Gameboy {
-    fn new() -> Gameboy {
-        Gameboy {
-            cpu: Cpu::new(),
-            gpu: Gpu::new(),
-            mmu: Mmu::new(),
+fn main() {
+    let mut arguments = env::args().skip(1);
+    let mut sync_rtc = false;
+    let mut boot_rom_path = None;
+    let mut rom_path = None;
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--sync-rtc" => sync_rtc = true,
+            "--boot-rom" => boot_rom_path = Some(arguments.next().unwrap_or_else(|| usage())),
+            _ if argument.starts_with("--") => usage(),
+            _ => rom_path = Some(argument),
         }
     }
+    let rom_path = rom_path.unwrap_or_else(|| usage());
 
-    fn run(&mut self) {
-        loop {
-            self.cpu.step(&mut self.mmu);
-            self.gpu.step(&mut self.mmu);
+    let mut cartridge = match Cartridge::from_file(&rom_path) {
+        Ok(cartridge) => cartridge,
+        Err(error) => {
+            eprintln!("{}: {}", rom_path, error);
+            process::exit(1);
         }
+    };
+
+    if !cartridge.global_checksum_matches() {
+        eprintln!(
+            "{}: warning: global checksum mismatch: expected 0x{:04X}, computed 0x{:04X}",
+            rom_path,
+            cartridge.header().global_checksum,
+            cartridge.global_checksum()
+        );
     }
-}
 
-fn main() {
-    let mut gameboy = Gameboy::new();
-    gameboy.run();
+    if sync_rtc {
+        cartridge.sync_rtc_to_host_time();
+    }
+
+    let mut gameboy = match boot_rom_path {
+        Some(boot_rom_path) => match BootRom::from_file(&boot_rom_path) {
+            Ok(boot_rom) => Gameboy::with_boot_rom(cartridge, boot_rom),
+            Err(error) => {
+                eprintln!("{}: {}", boot_rom_path, error);
+                process::exit(1);
+            }
+        },
+        None => Gameboy::new(cartridge),
+    };
+    while gameboy.run_frame() {
+        if let Some(error) = gameboy.take_save_error() {
+            eprintln!("cannot write save file: {}", error);
+        }
+    }
+    eprintln!("emulation stopped: {:?}", gameboy.cpu_state());
+    if let Err(error) = gameboy.flush_save() {
+        eprintln!("cannot write save file: {}", error);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,70 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+#[derive(Debug)]
+pub enum BootRomError {
+    Io(io::Error),
+    InvalidSize(usize),
+}
+
+impl fmt::Display for BootRomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BootRomError::Io(error) => write!(f, "cannot read boot ROM file: {}", error),
+            BootRomError::InvalidSize(size) => write!(
+                f,
+                "boot ROM must be {} (DMG) or {} (CGB) bytes, got {}",
+                BootRom::DMG_SIZE,
+                BootRom::CGB_SIZE,
+                size
+            ),
+        }
+    }
+}
+
+impl Error for BootRomError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BootRomError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BootRomError {
+    fn from(error: io::Error) -> BootRomError {
+        BootRomError::Io(error)
+    }
+}
+
+pub struct BootRom {
+    data: Vec<u8>,
+}
+
+impl BootRom {
+    pub const DMG_SIZE: usize = 0x100;
+    pub const CGB_SIZE: usize = 0x900;
+
+    // CGB boot ROMs leave a hole at 0x0100 - 0x01FF so the cartridge header stays visible
+    const HEADER_START: usize = 0x100;
+    const HEADER_END: usize = 0x200;
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<BootRom, BootRomError> {
+        BootRom::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<BootRom, BootRomError> {
+        match data.len() {
+            BootRom::DMG_SIZE | BootRom::CGB_SIZE => Ok(BootRom { data }),
+            size => Err(BootRomError::InvalidSize(size)),
+        }
+    }
+
+    pub fn is_mapped(&self, address: u16) -> bool {
+        let address = address as usize;
+        address < BootRom::HEADER_START || (BootRom::HEADER_END..self.data.len()).contains(&address)
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        self.data[address as usize]
+    }
+}
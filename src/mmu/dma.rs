@@ -0,0 +1,52 @@
+// copies 0xA0 bytes from XX00 - XX9F into OAM, one byte per M-cycle
+pub struct Dma {
+    register: u8,
+    // a write takes one M-cycle to set up the transfer, the previous transfer keeps running meanwhile
+    requested: Option<u16>,
+    starting: Option<u16>,
+    source: Option<u16>,
+}
+
+impl Dma {
+    pub const LENGTH: u16 = 0xA0;
+
+    pub fn new() -> Dma {
+        Dma {
+            register: 0x00,
+            requested: None,
+            starting: None,
+            source: None,
+        }
+    }
+
+    pub fn skip_boot(&mut self) {
+        self.register = 0xFF;
+    }
+
+    pub fn read_register(&self) -> u8 {
+        self.register
+    }
+
+    pub fn start(&mut self, value: u8) {
+        self.register = value;
+        // above WRAM the DMG external bus only sees the echo of WRAM
+        let page = if value >= 0xE0 { value - 0x20 } else { value };
+        self.requested = Some(u16::from(page) << 8);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.source.is_some()
+    }
+
+    // advances by one M-cycle and returns the address of the byte to copy in it
+    pub fn tick(&mut self) -> Option<u16> {
+        if let Some(source) = self.starting.take() {
+            self.source = Some(source);
+        }
+        self.starting = self.requested.take();
+
+        let source = self.source?;
+        self.source = if source & 0xFF < Dma::LENGTH - 1 { Some(source + 1) } else { None };
+        Some(source)
+    }
+}
@@ -0,0 +1,62 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    // ordered from the highest to the lowest priority
+    pub const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::LcdStat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad,
+    ];
+
+    pub fn mask(self) -> u8 {
+        match self {
+            Interrupt::VBlank => 0b0000_0001,
+            Interrupt::LcdStat => 0b0000_0010,
+            Interrupt::Timer => 0b0000_0100,
+            Interrupt::Serial => 0b0000_1000,
+            Interrupt::Joypad => 0b0001_0000,
+        }
+    }
+
+    pub fn vector(self) -> u16 {
+        match self {
+            Interrupt::VBlank => 0x40,
+            Interrupt::LcdStat => 0x48,
+            Interrupt::Timer => 0x50,
+            Interrupt::Serial => 0x58,
+            Interrupt::Joypad => 0x60,
+        }
+    }
+
+    pub fn highest_priority(flags: u8) -> Option<Interrupt> {
+        Interrupt::ALL
+            .iter()
+            .copied()
+            .find(|interrupt| flags & interrupt.mask() != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrupt_priority_test() {
+        assert_eq!(Interrupt::highest_priority(0b0000_0000), None);
+        assert_eq!(Interrupt::highest_priority(0b0001_1111), Some(Interrupt::VBlank));
+        assert_eq!(Interrupt::highest_priority(0b0001_1110), Some(Interrupt::LcdStat));
+        assert_eq!(Interrupt::highest_priority(0b0001_1100), Some(Interrupt::Timer));
+        assert_eq!(Interrupt::highest_priority(0b0001_1000), Some(Interrupt::Serial));
+        assert_eq!(Interrupt::highest_priority(0b0001_0000), Some(Interrupt::Joypad));
+        assert_eq!(Interrupt::highest_priority(0b1110_0000), None);
+    }
+}
@@ -1,9 +1,17 @@
 pub struct Memory {
-
+    data: Vec<u8>,
 }
 
 impl Memory {
-    pub fn new() -> Memory {
-        Memory {}
+    pub fn new(size: usize) -> Memory {
+        Memory { data: vec![0; size] }
+    }
+
+    pub fn read_byte(&self, offset: u16) -> u8 {
+        self.data[offset as usize]
+    }
+
+    pub fn write_byte(&mut self, offset: u16, value: u8) {
+        self.data[offset as usize] = value;
     }
 }
@@ -1,25 +1,589 @@
-//mod memory;
+mod boot_rom;
+mod dma;
+mod interrupt;
+mod memory;
 
-//use memory::Memory;
+pub use boot_rom::{BootRom, BootRomError};
+pub use interrupt::Interrupt;
+pub(crate) use memory::Memory;
+use super::{apu::Apu, cartridge::{Cartridge, CgbFlag}, gpu::Gpu, joypad::Joypad, serial::Serial, timer::Timer};
+use dma::Dma;
 
 pub struct Mmu {
-    memory: [u8; Mmu::TOTAL_MEMORY_SIZE],
+    cartridge: Cartridge,
+    boot_rom: Option<BootRom>,
+    gpu: Gpu,
+    dma: Dma,
+    timer: Timer,
+    joypad: Joypad,
+    serial: Serial,
+    apu: Apu,
+    wram: Memory,
+    hram: Memory,
+    interrupt_enable: u8,
+    interrupt_flag: u8,
 }
 
 impl Mmu {
-    const TOTAL_MEMORY_SIZE: usize = 0xFFFF;
+    const WRAM_SIZE: usize = 0x2000;
+    const HRAM_SIZE: usize = 0x7F;
 
-    pub fn new() -> Mmu {
+    const WRAM_START: u16 = 0xC000;
+    const ECHO_RAM_START: u16 = 0xE000;
+    const IO_START: u16 = 0xFF00;
+    const HRAM_START: u16 = 0xFF80;
+
+    const OAM_START: u16 = 0xFE00;
+
+    const FRAME_SEQUENCER_BIT: u16 = 0x1000;
+
+    const JOYPAD_ADDRESS: u16 = 0xFF00;
+    const DIVIDER_ADDRESS: u16 = 0xFF04;
+    const INTERRUPT_FLAG_ADDRESS: u16 = 0xFF0F;
+    const DMA_ADDRESS: u16 = 0xFF46;
+    const BOOT_ROM_DISABLE_ADDRESS: u16 = 0xFF50;
+    const INTERRUPT_ENABLE_ADDRESS: u16 = 0xFFFF;
+
+    // cartridges made for the CGB get its flavour of the audio and serial hardware
+    pub fn new(cartridge: Cartridge) -> Mmu {
+        let cgb = cartridge.header().cgb_flag != CgbFlag::DmgOnly;
+        Mmu {
+            cartridge,
+            boot_rom: None,
+            gpu: Gpu::new(),
+            dma: Dma::new(),
+            timer: Timer::new(),
+            joypad: Joypad::new(),
+            serial: if cgb { Serial::cgb() } else { Serial::new() },
+            apu: if cgb { Apu::cgb() } else { Apu::new() },
+            wram: Memory::new(Mmu::WRAM_SIZE),
+            hram: Memory::new(Mmu::HRAM_SIZE),
+            interrupt_enable: 0x0,
+            interrupt_flag: 0x0,
+        }
+    }
+
+    // the boot ROM covers the start of the cartridge ROM until it unmaps itself through 0xFF50
+    pub fn with_boot_rom(cartridge: Cartridge, boot_rom: BootRom) -> Mmu {
         Mmu {
-            memory: [0; Mmu::TOTAL_MEMORY_SIZE],
+            boot_rom: Some(boot_rom),
+            ..Mmu::new(cartridge)
         }
     }
 
+    // puts I/O registers into the state the DMG boot ROM leaves them in
+    pub fn skip_boot(&mut self) {
+        self.gpu.skip_boot();
+        self.dma.skip_boot();
+        self.timer.skip_boot();
+        self.joypad.skip_boot();
+        self.apu.skip_boot();
+        self.boot_rom = None;
+        self.interrupt_flag = Interrupt::VBlank.mask();
+        self.interrupt_enable = 0x00;
+    }
+
+    // while OAM DMA owns the bus the CPU only reaches I/O registers and HRAM
     pub fn read_byte(&self, address: u16) -> u8 {
-        self.memory[address as usize]
+        if self.dma.is_active() && address < Mmu::IO_START {
+            return 0xFF;
+        }
+        self.read_bus(address)
+    }
+
+    fn read_bus(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x7FFF => match &self.boot_rom {
+                Some(boot_rom) if boot_rom.is_mapped(address) => boot_rom.read_byte(address),
+                _ => self.cartridge.read_byte(address),
+            },
+            0x8000..=0x9FFF => self.gpu.read_byte(address),
+            0xA000..=0xBFFF => self.cartridge.read_byte(address),
+            0xC000..=0xDFFF => self.wram.read_byte(address - Mmu::WRAM_START),
+            // echo of 0xC000 - 0xDDFF
+            0xE000..=0xFDFF => self.wram.read_byte(address - Mmu::ECHO_RAM_START),
+            0xFE00..=0xFE9F => self.gpu.read_byte(address),
+            0xFEA0..=0xFEFF => 0x00,
+            0xFF00..=0xFF7F => self.read_io(address),
+            0xFF80..=0xFFFE => self.hram.read_byte(address - Mmu::HRAM_START),
+            Mmu::INTERRUPT_ENABLE_ADDRESS => self.interrupt_enable,
+        }
     }
 
     pub fn write_byte(&mut self, address: u16, value: u8) {
-        self.memory[address as usize] = value;
+        if self.dma.is_active() && address < Mmu::IO_START {
+            return;
+        }
+        match address {
+            0x0000..=0x7FFF => self.cartridge.write_byte(address, value),
+            0x8000..=0x9FFF => self.gpu.write_byte(address, value),
+            0xA000..=0xBFFF => self.cartridge.write_byte(address, value),
+            0xC000..=0xDFFF => self.wram.write_byte(address - Mmu::WRAM_START, value),
+            0xE000..=0xFDFF => self.wram.write_byte(address - Mmu::ECHO_RAM_START, value),
+            0xFE00..=0xFE9F => self.gpu.write_byte(address, value),
+            0xFEA0..=0xFEFF => (),
+            0xFF00..=0xFF7F => self.write_io(address, value),
+            0xFF80..=0xFFFE => self.hram.write_byte(address - Mmu::HRAM_START, value),
+            Mmu::INTERRUPT_ENABLE_ADDRESS => self.interrupt_enable = value,
+        }
+    }
+
+    fn read_io(&self, address: u16) -> u8 {
+        match address {
+            Mmu::JOYPAD_ADDRESS => self.joypad.read_register(),
+            0xFF01..=0xFF02 => self.serial.read_byte(address),
+            0xFF04..=0xFF07 => self.timer.read_byte(address),
+            Mmu::INTERRUPT_FLAG_ADDRESS => 0b1110_0000 | self.interrupt_flag,
+            0xFF10..=0xFF26 | 0xFF30..=0xFF3F => self.apu.read_byte(address),
+            Mmu::DMA_ADDRESS => self.dma.read_register(),
+            Mmu::BOOT_ROM_DISABLE_ADDRESS => 0xFF,
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.gpu.read_byte(address),
+            // registers not wired to anything on DMG, reads return all bits set
+            _ => 0xFF,
+        }
+    }
+
+    fn write_io(&mut self, address: u16, value: u8) {
+        match address {
+            Mmu::JOYPAD_ADDRESS => self.interrupt_flag |= self.joypad.select(value),
+            0xFF01..=0xFF02 => self.serial.write_byte(address, value),
+            Mmu::DIVIDER_ADDRESS => {
+                // resetting DIV while bit 4 is set is a falling edge for the frame sequencer too
+                if self.timer.divider() & Mmu::FRAME_SEQUENCER_BIT != 0 {
+                    self.apu.clock_frame_sequencer();
+                }
+                self.timer.write_byte(address, value);
+            }
+            0xFF05..=0xFF07 => self.timer.write_byte(address, value),
+            Mmu::INTERRUPT_FLAG_ADDRESS => self.interrupt_flag = value & 0b0001_1111,
+            0xFF10..=0xFF26 | 0xFF30..=0xFF3F => self.apu.write_byte(address, value),
+            // writing again mid transfer restarts it from the new source
+            Mmu::DMA_ADDRESS => self.dma.start(value),
+            // once unmapped the boot ROM stays unmapped until reset
+            Mmu::BOOT_ROM_DISABLE_ADDRESS if value != 0 => self.boot_rom = None,
+            Mmu::BOOT_ROM_DISABLE_ADDRESS => (),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.gpu.write_byte(address, value),
+            _ => (),
+        }
+    }
+
+    // cycles always come in whole M-cycles from the CPU
+    pub fn step(&mut self, cycles: u32) {
+        self.cartridge.step(cycles);
+        for _ in 0..cycles / 4 {
+            if let Some(source) = self.dma.tick() {
+                let value = self.read_bus(source);
+                self.gpu.write_byte(Mmu::OAM_START + (source & 0xFF), value);
+            }
+        }
+        // the frame sequencer runs off falling edges of DIV bit 4, bit 12 of the internal divider
+        let divider = u32::from(self.timer.divider());
+        let edges = ((divider + cycles) >> 13) - (divider >> 13);
+        self.interrupt_flag |= self.timer.step(cycles);
+        self.interrupt_flag |= self.serial.step(cycles);
+        for _ in 0..edges {
+            self.apu.clock_frame_sequencer();
+        }
+        self.apu.step(cycles);
+        self.interrupt_flag |= self.gpu.step(cycles);
+    }
+
+    pub fn gpu(&self) -> &Gpu {
+        &self.gpu
+    }
+
+    pub fn gpu_mut(&mut self) -> &mut Gpu {
+        &mut self.gpu
     }
-}
\ No newline at end of file
+
+    pub fn apu(&self) -> &Apu {
+        &self.apu
+    }
+
+    pub fn apu_mut(&mut self) -> &mut Apu {
+        &mut self.apu
+    }
+
+    pub fn serial_mut(&mut self) -> &mut Serial {
+        &mut self.serial
+    }
+
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    // pressed buttons as a set of Button masks
+    pub fn buttons(&self) -> u8 {
+        self.joypad.pressed()
+    }
+
+    pub fn set_buttons(&mut self, buttons: u8) {
+        self.interrupt_flag |= self.joypad.set_pressed(buttons);
+    }
+
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag |= interrupt.mask();
+    }
+
+    pub fn acknowledge_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag &= !interrupt.mask();
+    }
+
+    pub fn pending_interrupts(&self) -> u8 {
+        self.interrupt_enable & self.interrupt_flag & 0b0001_1111
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{test_cartridge, test_rom, update_checksums};
+    use crate::joypad::Button;
+
+    #[test]
+    fn mmu_interrupt_registers_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1110_0000);
+        assert_eq!(mmu.read_byte(0xFFFF), 0b0000_0000);
+
+        mmu.write_byte(0xFFFF, 0b0000_0101);
+        mmu.request_interrupt(Interrupt::Timer);
+        mmu.request_interrupt(Interrupt::Serial);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1110_1100);
+        assert_eq!(mmu.pending_interrupts(), 0b0000_0100);
+
+        mmu.acknowledge_interrupt(Interrupt::Timer);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1110_1000);
+        assert_eq!(mmu.pending_interrupts(), 0b0000_0000);
+
+        mmu.write_byte(0xFF0F, 0b1111_1111);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1111_1111);
+        assert_eq!(mmu.pending_interrupts(), 0b0000_0101);
+    }
+
+    #[test]
+    fn mmu_rom_is_read_only_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0x0100, 0xAB);
+        mmu.write_byte(0x7FFF, 0xAB);
+        assert_eq!(mmu.read_byte(0x0100), 0x00);
+        assert_eq!(mmu.read_byte(0x7FFF), 0x00);
+    }
+
+    #[test]
+    fn mmu_ram_regions_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        let addresses = vec![0x8000, 0x9FFF, 0xC000, 0xDFFF, 0xFE00, 0xFE9F, 0xFF80, 0xFFFE, 0xFFFF];
+        for (value, address) in addresses.iter().enumerate() {
+            mmu.write_byte(*address, value as u8 + 1);
+        }
+        for (value, address) in addresses.iter().enumerate() {
+            assert_eq!(mmu.read_byte(*address), value as u8 + 1);
+        }
+    }
+
+    #[test]
+    fn mmu_external_ram_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0xA000, 0xAB);
+        assert_eq!(mmu.read_byte(0xA000), 0xFF);
+
+        let mut mmu = Mmu::new(Cartridge::from_bytes(test_rom(0x08, 0x00, 0x02)).unwrap());
+        mmu.write_byte(0xA000, 0xAB);
+        mmu.write_byte(0xBFFF, 0xCD);
+        assert_eq!(mmu.read_byte(0xA000), 0xAB);
+        assert_eq!(mmu.read_byte(0xBFFF), 0xCD);
+    }
+
+    #[test]
+    fn mmu_echo_ram_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0xC000, 0x12);
+        mmu.write_byte(0xDDFF, 0x34);
+        assert_eq!(mmu.read_byte(0xE000), 0x12);
+        assert_eq!(mmu.read_byte(0xFDFF), 0x34);
+
+        mmu.write_byte(0xE123, 0x56);
+        assert_eq!(mmu.read_byte(0xC123), 0x56);
+
+        // 0xDE00 - 0xDFFF is not mirrored, 0xFE00 is OAM
+        mmu.write_byte(0xDE00, 0x78);
+        assert_eq!(mmu.read_byte(0xFE00), 0x00);
+    }
+
+    #[test]
+    fn mmu_unusable_area_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0xFEA0, 0xAB);
+        mmu.write_byte(0xFEFF, 0xAB);
+        assert_eq!(mmu.read_byte(0xFEA0), 0x00);
+        assert_eq!(mmu.read_byte(0xFEFF), 0x00);
+    }
+
+    #[test]
+    fn mmu_boot_rom_test() {
+        let mut rom = test_rom(0x00, 0x00, 0x00);
+        rom[0x0000] = 0x12;
+        rom[0x0100] = 0x34;
+        rom[0x0200] = 0x56;
+        crate::cartridge::update_checksums(&mut rom);
+        let cartridge = || Cartridge::from_bytes(rom.clone()).unwrap();
+
+        let mut boot_rom = vec![0; BootRom::DMG_SIZE];
+        boot_rom[0x00] = 0xAB;
+        boot_rom[0xFF] = 0xCD;
+        let mut mmu = Mmu::with_boot_rom(cartridge(), BootRom::from_bytes(boot_rom).unwrap());
+        assert_eq!(mmu.read_byte(0x0000), 0xAB);
+        assert_eq!(mmu.read_byte(0x00FF), 0xCD);
+        assert_eq!(mmu.read_byte(0x0100), 0x34);
+
+        mmu.write_byte(0xFF50, 0x00);
+        assert_eq!(mmu.read_byte(0x0000), 0xAB);
+        mmu.write_byte(0xFF50, 0x01);
+        assert_eq!(mmu.read_byte(0x0000), 0x12);
+        assert_eq!(mmu.read_byte(0xFF50), 0xFF);
+
+        // the cartridge header shows through the CGB boot ROM
+        let mut boot_rom = vec![0xEE; BootRom::CGB_SIZE];
+        boot_rom[0x08FF] = 0xEF;
+        let mmu = Mmu::with_boot_rom(cartridge(), BootRom::from_bytes(boot_rom).unwrap());
+        assert_eq!(mmu.read_byte(0x0000), 0xEE);
+        assert_eq!(mmu.read_byte(0x0100), 0x34);
+        assert_eq!(mmu.read_byte(0x0200), 0xEE);
+        assert_eq!(mmu.read_byte(0x08FF), 0xEF);
+        assert_eq!(mmu.read_byte(0x0900), 0x00);
+
+        assert!(matches!(
+            BootRom::from_bytes(vec![0; 0x200]),
+            Err(BootRomError::InvalidSize(0x200))
+        ));
+    }
+
+    #[test]
+    fn mmu_skip_boot_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.skip_boot();
+        assert_eq!(mmu.read_byte(0xFF0F), 0xE1);
+        assert_eq!(mmu.read_byte(0xFF40), 0x91);
+        assert_eq!(mmu.read_byte(0xFF47), 0xFC);
+        assert_eq!(mmu.read_byte(0xFF26), 0xF1);
+        assert_eq!(mmu.read_byte(0xFF11), 0xBF);
+        assert_eq!(mmu.read_byte(0xFF19), 0xBF);
+        assert_eq!(mmu.read_byte(0xFF00), 0xCF);
+        assert_eq!(mmu.read_byte(0xFF02), 0x7E);
+        assert_eq!(mmu.read_byte(0xFF04), 0xAB);
+        assert_eq!(mmu.read_byte(0xFF07), 0xF8);
+        assert_eq!(mmu.read_byte(0xFF46), 0xFF);
+        assert_eq!(mmu.read_byte(0xFF41), 0x85);
+        assert_eq!(mmu.read_byte(0xFF44), 0x00);
+        assert_eq!(mmu.read_byte(0xFFFF), 0x00);
+    }
+
+    #[test]
+    fn mmu_gpu_interrupts_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0xFF40, 0x91);
+        mmu.write_byte(0xFF41, 0b0000_1000);
+        mmu.step(252);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1110_0010);
+
+        mmu.write_byte(0xFF0F, 0x00);
+        mmu.step(144 * 456 - 252);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1110_0011);
+    }
+
+    #[test]
+    fn mmu_oam_dma_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        for offset in 0..Dma::LENGTH {
+            mmu.write_byte(0xC000 + offset, offset as u8);
+            mmu.write_byte(0xC100 + offset, !offset as u8);
+        }
+        mmu.write_byte(0xFF80, 0x12);
+
+        mmu.write_byte(0xFF46, 0xC0);
+        assert_eq!(mmu.read_byte(0xFF46), 0xC0);
+        // the bus stays free during the setup M-cycle
+        mmu.step(4);
+        assert_eq!(mmu.read_byte(0xC001), 0x01);
+
+        mmu.step(4);
+        assert_eq!(mmu.read_byte(0xC001), 0xFF);
+        assert_eq!(mmu.read_byte(0xFE00), 0xFF);
+        assert_eq!(mmu.read_byte(0x0000), 0xFF);
+        assert_eq!(mmu.read_byte(0xFF80), 0x12);
+        mmu.write_byte(0xC001, 0xAB);
+        mmu.write_byte(0xFF81, 0x34);
+        assert_eq!(mmu.read_byte(0xFF81), 0x34);
+
+        mmu.step(4 * 158);
+        assert_eq!(mmu.read_byte(0xFE00), 0xFF);
+        mmu.step(4);
+        for offset in 0..Dma::LENGTH {
+            assert_eq!(mmu.read_byte(0xFE00 + offset), offset as u8);
+        }
+        assert_eq!(mmu.read_byte(0xC001), 0x01);
+
+        // a restart keeps the bus blocked and copies everything from the new source
+        mmu.write_byte(0xFF46, 0xC0);
+        mmu.step(4 * 81);
+        mmu.write_byte(0xFF46, 0xC1);
+        mmu.step(4);
+        assert_eq!(mmu.read_byte(0xC000), 0xFF);
+        mmu.step(4 * 160);
+        assert_eq!(mmu.read_byte(0xC000), 0x00);
+        for offset in 0..Dma::LENGTH {
+            assert_eq!(mmu.read_byte(0xFE00 + offset), !offset as u8);
+        }
+    }
+
+    #[test]
+    fn mmu_oam_dma_sources_test() {
+        let mut rom = test_rom(0x00, 0x00, 0x00);
+        rom[0x0200] = 0x12;
+        rom[0x029F] = 0x34;
+        crate::cartridge::update_checksums(&mut rom);
+        let mut mmu = Mmu::new(Cartridge::from_bytes(rom).unwrap());
+        mmu.write_byte(0xFF46, 0x02);
+        mmu.step(4 * 161);
+        assert_eq!(mmu.read_byte(0xFE00), 0x12);
+        assert_eq!(mmu.read_byte(0xFE9F), 0x34);
+
+        // echo RAM and anything above it reads from WRAM
+        mmu.write_byte(0xC000, 0x56);
+        mmu.write_byte(0xDE9F, 0x78);
+        mmu.write_byte(0xFF46, 0xE0);
+        mmu.step(4 * 161);
+        assert_eq!(mmu.read_byte(0xFE00), 0x56);
+        mmu.write_byte(0xFF46, 0xFE);
+        mmu.step(4 * 161);
+        assert_eq!(mmu.read_byte(0xFE9F), 0x78);
+        assert_eq!(mmu.read_byte(0xFF46), 0xFE);
+    }
+
+    #[test]
+    fn mmu_timer_interrupt_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0xFF06, 0x80);
+        mmu.write_byte(0xFF05, 0xFE);
+        mmu.write_byte(0xFF07, 0x05);
+        mmu.step(32);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1110_0000);
+        mmu.step(4);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1110_0100);
+        assert_eq!(mmu.read_byte(0xFF05), 0x80);
+    }
+
+    #[test]
+    fn mmu_joypad_interrupt_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0xFF00, 0x10);
+        mmu.set_buttons(Button::Up.mask());
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1110_0000);
+        mmu.set_buttons(Button::Up.mask() | Button::Start.mask());
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1111_0000);
+        assert_eq!(mmu.read_byte(0xFF00), 0xD7);
+
+        // selecting a group with a key already held also pulls a line low
+        mmu.write_byte(0xFF0F, 0x00);
+        mmu.write_byte(0xFF00, 0x20);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1111_0000);
+        assert_eq!(mmu.read_byte(0xFF00), 0xEB);
+    }
+
+    #[test]
+    fn mmu_serial_interrupt_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0xFF01, 0x42);
+        mmu.write_byte(0xFF02, 0x81);
+        mmu.step(8 * 512);
+        assert_eq!(mmu.read_byte(0xFF0F), 0b1110_1000);
+        assert_eq!(mmu.read_byte(0xFF01), 0xFF);
+        assert_eq!(mmu.read_byte(0xFF02), 0x7F);
+    }
+
+    #[test]
+    fn mmu_cgb_serial_fast_clock_test() {
+        // the fast clock bit is ignored on DMG
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0xFF02, 0x83);
+        mmu.step(8 * 16);
+        assert_eq!(mmu.read_byte(0xFF02), 0xFF);
+        let mut mmu = Mmu::new(cgb_cartridge());
+        mmu.write_byte(0xFF02, 0x83);
+        mmu.step(8 * 16);
+        assert_eq!(mmu.read_byte(0xFF0F) & Interrupt::Serial.mask(), Interrupt::Serial.mask());
+        assert_eq!(mmu.read_byte(0xFF02), 0x7F);
+    }
+
+    #[test]
+    fn mmu_frame_sequencer_test() {
+        // channel 2 with a length of 1 stops on the first frame sequencer step
+        fn trigger_channel2(mmu: &mut Mmu) {
+            mmu.write_byte(0xFF16, 0x3F);
+            mmu.write_byte(0xFF17, 0xF0);
+            mmu.write_byte(0xFF19, 0xC0);
+        }
+
+        // after the boot ROM DIV bit 4 falls once the divider reaches 0xC000
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.skip_boot();
+        trigger_channel2(&mut mmu);
+        mmu.step(0xC000 - 0xABCC - 4);
+        assert_eq!(mmu.read_byte(0xFF26), 0xF3);
+        mmu.step(4);
+        assert_eq!(mmu.read_byte(0xFF26), 0xF1);
+
+        // resetting DIV with bit 4 set clocks it too
+        let mut mmu = Mmu::new(test_cartridge());
+        mmu.write_byte(0xFF26, 0x80);
+        trigger_channel2(&mut mmu);
+        mmu.step(0x1000);
+        assert_eq!(mmu.read_byte(0xFF26), 0xF2);
+        mmu.write_byte(0xFF04, 0x00);
+        assert_eq!(mmu.read_byte(0xFF26), 0xF0);
+    }
+
+    fn cgb_cartridge() -> Cartridge {
+        let mut rom = test_rom(0x00, 0x00, 0x00);
+        rom[0x0143] = 0x80;
+        update_checksums(&mut rom);
+        Cartridge::from_bytes(rom).unwrap()
+    }
+
+    #[test]
+    fn mmu_cgb_apu_test() {
+        // a length of 1 loaded before a power cycle
+        fn power_cycled_channel2(mmu: &mut Mmu) {
+            mmu.write_byte(0xFF26, 0x80);
+            mmu.write_byte(0xFF16, 0x3F);
+            mmu.write_byte(0xFF26, 0x00);
+            mmu.write_byte(0xFF16, 0x3F);
+            mmu.write_byte(0xFF26, 0x80);
+            mmu.write_byte(0xFF17, 0xF0);
+            mmu.write_byte(0xFF19, 0xC0);
+            mmu.step(0x2000);
+        }
+
+        // DMG keeps the length through the power cycle, CGB clears it and ignores the write while off
+        let mut mmu = Mmu::new(test_cartridge());
+        power_cycled_channel2(&mut mmu);
+        assert_eq!(mmu.read_byte(0xFF26), 0xF0);
+        let mut mmu = Mmu::new(cgb_cartridge());
+        power_cycled_channel2(&mut mmu);
+        assert_eq!(mmu.read_byte(0xFF26), 0xF2);
+    }
+
+    #[test]
+    fn mmu_unused_io_registers_test() {
+        let mut mmu = Mmu::new(test_cartridge());
+        for address in [0xFF03, 0xFF08, 0xFF0E, 0xFF15, 0xFF1F, 0xFF27, 0xFF2F, 0xFF4C, 0xFF7F].iter() {
+            mmu.write_byte(*address, 0x00);
+            assert_eq!(mmu.read_byte(*address), 0xFF);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use super::mmu::Interrupt;
+
+// the other end of the link cable
+pub trait SerialDevice {
+    // swaps one bit in each direction on every clock pulse, most significant bit first
+    fn exchange_bit(&mut self, bit: bool) -> bool;
+
+    // polled while a transfer waits on an external clock, returns how many clock pulses arrived within the elapsed dots
+    fn external_clock(&mut self, _cycles: u32) -> u32 {
+        0
+    }
+}
+
+pub struct Serial {
+    sb: u8,
+    sc: u8,
+    cgb: bool,
+    dots: u32,
+    bits: u8,
+    device: Option<Box<dyn SerialDevice>>,
+}
+
+impl Serial {
+    const SB_ADDRESS: u16 = 0xFF01;
+    const SC_ADDRESS: u16 = 0xFF02;
+
+    const TRANSFER_START: u8 = 0b1000_0000;
+    const FAST_CLOCK: u8 = 0b0000_0010;
+    const INTERNAL_CLOCK: u8 = 0b0000_0001;
+
+    // 8192 Hz, or 262144 Hz with the CGB fast clock
+    const BIT_DOTS: u32 = 512;
+    const FAST_BIT_DOTS: u32 = 16;
+
+    pub fn new() -> Serial {
+        Serial {
+            sb: 0x00,
+            sc: 0x00,
+            cgb: false,
+            dots: 0,
+            bits: 0,
+            device: None,
+        }
+    }
+
+    pub fn cgb() -> Serial {
+        Serial { cgb: true, ..Serial::new() }
+    }
+
+    pub fn connect(&mut self, device: Box<dyn SerialDevice>) {
+        self.device = Some(device);
+    }
+
+    pub fn disconnect(&mut self) -> Option<Box<dyn SerialDevice>> {
+        self.device.take()
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            Serial::SB_ADDRESS => self.sb,
+            Serial::SC_ADDRESS => !self.writable_sc_bits() | self.sc,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        match address {
+            Serial::SB_ADDRESS => self.sb = value,
+            Serial::SC_ADDRESS => {
+                self.sc = value & self.writable_sc_bits();
+                self.dots = 0;
+                self.bits = 0;
+            }
+            _ => (),
+        }
+    }
+
+    pub fn step(&mut self, cycles: u32) -> u8 {
+        if self.sc & Serial::TRANSFER_START == 0 {
+            return 0;
+        }
+
+        // with an external clock the transfer waits for whoever is on the other end
+        let mut interrupts = 0;
+        if self.sc & Serial::INTERNAL_CLOCK == 0 {
+            let pulses = match &mut self.device {
+                Some(device) => device.external_clock(cycles),
+                None => 0,
+            };
+            for _ in 0..pulses {
+                if self.sc & Serial::TRANSFER_START == 0 {
+                    break;
+                }
+                interrupts |= self.shift();
+            }
+            return interrupts;
+        }
+
+        let bit_dots = if self.sc & Serial::FAST_CLOCK != 0 { Serial::FAST_BIT_DOTS } else { Serial::BIT_DOTS };
+        self.dots += cycles;
+        while self.dots >= bit_dots && self.sc & Serial::TRANSFER_START != 0 {
+            self.dots -= bit_dots;
+            interrupts |= self.shift();
+        }
+        interrupts
+    }
+
+    fn writable_sc_bits(&self) -> u8 {
+        if self.cgb {
+            Serial::TRANSFER_START | Serial::FAST_CLOCK | Serial::INTERNAL_CLOCK
+        } else {
+            Serial::TRANSFER_START | Serial::INTERNAL_CLOCK
+        }
+    }
+
+    fn shift(&mut self) -> u8 {
+        let bit = self.sb & 0x80 != 0;
+        // a disconnected line is pulled high
+        let incoming = match &mut self.device {
+            Some(device) => device.exchange_bit(bit),
+            None => true,
+        };
+        self.sb = self.sb << 1 | incoming as u8;
+        self.bits += 1;
+        if self.bits < 8 {
+            return 0;
+        }
+        self.bits = 0;
+        self.sc &= !Serial::TRANSFER_START;
+        Interrupt::Serial.mask()
+    }
+}
+
+impl Default for Serial {
+    fn default() -> Serial {
+        Serial::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    // sends a fixed byte and records what it received
+    struct TestDevice {
+        send: u8,
+        received: Rc<RefCell<u8>>,
+        // dots per external clock pulse, 0 never clocks
+        clock_dots: u32,
+        dots: u32,
+    }
+
+    impl SerialDevice for TestDevice {
+        fn exchange_bit(&mut self, bit: bool) -> bool {
+            let mut received = self.received.borrow_mut();
+            *received = *received << 1 | bit as u8;
+            let incoming = self.send & 0x80 != 0;
+            self.send <<= 1;
+            incoming
+        }
+
+        fn external_clock(&mut self, cycles: u32) -> u32 {
+            if self.clock_dots == 0 {
+                return 0;
+            }
+            self.dots += cycles;
+            let pulses = self.dots / self.clock_dots;
+            self.dots %= self.clock_dots;
+            pulses
+        }
+    }
+
+    fn test_device(send: u8, clock_dots: u32) -> (Box<TestDevice>, Rc<RefCell<u8>>) {
+        let received = Rc::new(RefCell::new(0x00));
+        let device = TestDevice {
+            send,
+            received: Rc::clone(&received),
+            clock_dots,
+            dots: 0,
+        };
+        (Box::new(device), received)
+    }
+
+    #[test]
+    fn serial_internal_clock_test() {
+        let mut serial = Serial::new();
+        assert_eq!(serial.read_byte(0xFF02), 0x7E);
+        serial.write_byte(0xFF01, 0x12);
+        serial.write_byte(0xFF02, 0x83);
+        assert_eq!(serial.read_byte(0xFF02), 0xFF);
+
+        assert_eq!(serial.step(8 * 512 - 1), 0);
+        assert_eq!(serial.read_byte(0xFF01), 0x7F);
+        assert_eq!(serial.step(1), Interrupt::Serial.mask());
+        assert_eq!(serial.read_byte(0xFF01), 0xFF);
+        assert_eq!(serial.read_byte(0xFF02), 0x7F);
+        assert_eq!(serial.step(8 * 512), 0);
+
+        let (device, received) = test_device(0xA5, 0);
+        serial.connect(device);
+        serial.write_byte(0xFF01, 0x3C);
+        serial.write_byte(0xFF02, 0x81);
+        assert_eq!(serial.step(8 * 512), Interrupt::Serial.mask());
+        assert_eq!(serial.read_byte(0xFF01), 0xA5);
+        assert_eq!(*received.borrow(), 0x3C);
+    }
+
+    #[test]
+    fn serial_cgb_fast_clock_test() {
+        let mut serial = Serial::cgb();
+        serial.write_byte(0xFF02, 0x83);
+        assert_eq!(serial.read_byte(0xFF02), 0xFF);
+        assert_eq!(serial.step(8 * 16), Interrupt::Serial.mask());
+
+        serial.write_byte(0xFF02, 0x81);
+        assert_eq!(serial.read_byte(0xFF02), 0xFD);
+        assert_eq!(serial.step(8 * 16), 0);
+    }
+
+    #[test]
+    fn serial_external_clock_test() {
+        let mut serial = Serial::new();
+        serial.write_byte(0xFF01, 0x12);
+        serial.write_byte(0xFF02, 0x80);
+        assert_eq!(serial.step(100_000), 0);
+        assert_eq!(serial.read_byte(0xFF01), 0x12);
+        assert_eq!(serial.read_byte(0xFF02), 0xFE);
+
+        // the other end clocks every 64 dots, however the elapsed time is split up
+        let (device, received) = test_device(0x81, 64);
+        serial.connect(device);
+        for _ in 0..7 {
+            assert_eq!(serial.step(64), 0);
+        }
+        assert_eq!(serial.step(63), 0);
+        assert_eq!(serial.step(1), Interrupt::Serial.mask());
+        assert_eq!(serial.read_byte(0xFF01), 0x81);
+        assert_eq!(*received.borrow(), 0x12);
+        assert!(serial.disconnect().is_some());
+
+        // pulses past the end of a transfer are not shifted in
+        let (device, _) = test_device(0xFF, 64);
+        serial.connect(device);
+        serial.write_byte(0xFF01, 0x00);
+        serial.write_byte(0xFF02, 0x80);
+        assert_eq!(serial.step(16 * 64), Interrupt::Serial.mask());
+        assert_eq!(serial.read_byte(0xFF01), 0xFF);
+        assert_eq!(serial.read_byte(0xFF02), 0x7E);
+    }
+}
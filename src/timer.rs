@@ -0,0 +1,237 @@
+use super::mmu::Interrupt;
+
+pub struct Timer {
+    // DIV is the upper byte of a divider counting every dot
+    divider: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    // TIMA stays 0x00 for an M-cycle after overflowing before TMA is loaded
+    overflow_dots: u8,
+    // writes to TIMA are ignored in the M-cycle TMA is loaded
+    reload_dots: u8,
+}
+
+impl Timer {
+    const DIV_ADDRESS: u16 = 0xFF04;
+    const TIMA_ADDRESS: u16 = 0xFF05;
+    const TMA_ADDRESS: u16 = 0xFF06;
+    const TAC_ADDRESS: u16 = 0xFF07;
+
+    const TAC_ENABLE: u8 = 0x04;
+    const M_CYCLE_DOTS: u8 = 4;
+
+    pub fn new() -> Timer {
+        Timer {
+            divider: 0x0000,
+            tima: 0x00,
+            tma: 0x00,
+            tac: 0x00,
+            overflow_dots: 0,
+            reload_dots: 0,
+        }
+    }
+
+    pub fn skip_boot(&mut self) {
+        self.divider = 0xABCC;
+    }
+
+    pub fn divider(&self) -> u16 {
+        self.divider
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            Timer::DIV_ADDRESS => (self.divider >> 8) as u8,
+            Timer::TIMA_ADDRESS => self.tima,
+            Timer::TMA_ADDRESS => self.tma,
+            Timer::TAC_ADDRESS => 0b1111_1000 | self.tac,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        match address {
+            // clearing the divider can produce a falling edge on the selected bit
+            Timer::DIV_ADDRESS => self.update_divider(0),
+            Timer::TIMA_ADDRESS if self.reload_dots > 0 => (),
+            Timer::TIMA_ADDRESS => {
+                // a write during the overflow delay cancels the reload and the interrupt
+                self.tima = value;
+                self.overflow_dots = 0;
+            }
+            Timer::TMA_ADDRESS => {
+                self.tma = value;
+                if self.reload_dots > 0 {
+                    self.tima = value;
+                }
+            }
+            Timer::TAC_ADDRESS => {
+                // on DMG disabling the timer or switching the input bit can tick TIMA
+                let input = self.input();
+                self.tac = value & 0b0000_0111;
+                if input && !self.input() {
+                    self.increment_tima();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn step(&mut self, cycles: u32) -> u8 {
+        let mut interrupts = 0;
+        for _ in 0..cycles {
+            interrupts |= self.tick();
+        }
+        interrupts
+    }
+
+    fn tick(&mut self) -> u8 {
+        let mut interrupts = 0;
+        if self.reload_dots > 0 {
+            self.reload_dots -= 1;
+        }
+        if self.overflow_dots > 0 {
+            self.overflow_dots -= 1;
+            if self.overflow_dots == 0 {
+                self.tima = self.tma;
+                self.reload_dots = Timer::M_CYCLE_DOTS;
+                interrupts |= Interrupt::Timer.mask();
+            }
+        }
+        self.update_divider(self.divider.wrapping_add(1));
+        interrupts
+    }
+
+    // TIMA counts falling edges of the divider bit selected by TAC, gated by the enable bit
+    fn input(&self) -> bool {
+        let bit = match self.tac & 0b11 {
+            0b00 => 9,
+            0b01 => 3,
+            0b10 => 5,
+            _ => 7,
+        };
+        self.tac & Timer::TAC_ENABLE != 0 && self.divider & (1 << bit) != 0
+    }
+
+    fn update_divider(&mut self, divider: u16) {
+        let input = self.input();
+        self.divider = divider;
+        if input && !self.input() {
+            self.increment_tima();
+        }
+    }
+
+    fn increment_tima(&mut self) {
+        let (tima, overflow) = self.tima.overflowing_add(1);
+        self.tima = tima;
+        if overflow {
+            self.overflow_dots = Timer::M_CYCLE_DOTS;
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Timer {
+        Timer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_divider_test() {
+        let mut timer = Timer::new();
+        timer.step(255);
+        assert_eq!(timer.read_byte(0xFF04), 0x00);
+        timer.step(1);
+        assert_eq!(timer.read_byte(0xFF04), 0x01);
+        timer.step(256 * 0xFF);
+        assert_eq!(timer.read_byte(0xFF04), 0x00);
+
+        timer.step(0x1234);
+        timer.write_byte(0xFF04, 0xAB);
+        assert_eq!(timer.read_byte(0xFF04), 0x00);
+        assert_eq!(timer.divider(), 0x0000);
+        assert_eq!(timer.read_byte(0xFF07), 0xF8);
+    }
+
+    #[test]
+    fn timer_frequencies_test() {
+        for (tac, period) in [(0x04, 1024), (0x05, 16), (0x06, 64), (0x07, 256)].iter() {
+            let mut timer = Timer::new();
+            timer.write_byte(0xFF07, *tac);
+            timer.step(period - 1);
+            assert_eq!(timer.read_byte(0xFF05), 0x00);
+            timer.step(1);
+            assert_eq!(timer.read_byte(0xFF05), 0x01);
+            timer.step(period * 9);
+            assert_eq!(timer.read_byte(0xFF05), 0x0A);
+        }
+
+        let mut timer = Timer::new();
+        timer.write_byte(0xFF07, 0x01);
+        timer.step(1024);
+        assert_eq!(timer.read_byte(0xFF05), 0x00);
+    }
+
+    #[test]
+    fn timer_overflow_test() {
+        let mut timer = Timer::new();
+        timer.write_byte(0xFF06, 0xAB);
+        timer.write_byte(0xFF05, 0xFF);
+        timer.write_byte(0xFF07, 0x05);
+
+        // the reload and the interrupt come one M-cycle after the overflow
+        assert_eq!(timer.step(16), 0);
+        assert_eq!(timer.read_byte(0xFF05), 0x00);
+        assert_eq!(timer.step(4), Interrupt::Timer.mask());
+        assert_eq!(timer.read_byte(0xFF05), 0xAB);
+
+        // writing TIMA during the delay cancels the reload
+        timer.step(4);
+        timer.write_byte(0xFF05, 0xFF);
+        timer.step(8);
+        assert_eq!(timer.read_byte(0xFF05), 0x00);
+        timer.write_byte(0xFF05, 0x12);
+        assert_eq!(timer.step(4), 0);
+        assert_eq!(timer.read_byte(0xFF05), 0x12);
+
+        // during the reload TIMA writes are ignored and TMA writes go through to TIMA
+        timer.write_byte(0xFF05, 0xFF);
+        timer.step(12);
+        assert_eq!(timer.step(4), Interrupt::Timer.mask());
+        timer.write_byte(0xFF05, 0x34);
+        assert_eq!(timer.read_byte(0xFF05), 0xAB);
+        timer.write_byte(0xFF06, 0x56);
+        assert_eq!(timer.read_byte(0xFF05), 0x56);
+        timer.step(4);
+        timer.write_byte(0xFF05, 0x78);
+        assert_eq!(timer.read_byte(0xFF05), 0x78);
+    }
+
+    #[test]
+    fn timer_glitches_test() {
+        // resetting DIV while the selected bit is set ticks TIMA
+        let mut timer = Timer::new();
+        timer.write_byte(0xFF07, 0x05);
+        timer.step(8);
+        timer.write_byte(0xFF04, 0x00);
+        assert_eq!(timer.read_byte(0xFF05), 0x01);
+        timer.step(7);
+        timer.write_byte(0xFF04, 0x00);
+        assert_eq!(timer.read_byte(0xFF05), 0x01);
+
+        // so does disabling the timer or selecting a cleared bit
+        timer.step(8);
+        timer.write_byte(0xFF07, 0x01);
+        assert_eq!(timer.read_byte(0xFF05), 0x02);
+        timer.write_byte(0xFF07, 0x05);
+        timer.write_byte(0xFF07, 0x06);
+        assert_eq!(timer.read_byte(0xFF05), 0x03);
+        timer.write_byte(0xFF07, 0x05);
+        assert_eq!(timer.read_byte(0xFF05), 0x03);
+    }
+}